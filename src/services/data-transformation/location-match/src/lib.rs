@@ -0,0 +1,170 @@
+// src/lib.rs  
+
+// Location lookup shared by the pipelines that take a city name  
+
+use polars::prelude::*;  
+
+const MAX_SUGGESTIONS: usize = 3;  
+
+// Resolves `query` against the names in `column`, e.g. "Location" in weatherAUS.csv  
+pub fn resolve_location_in(df: &DataFrame, column: &str, query: &str) -> Result<String, String> {  
+    let known = known_locations(df, column)?;  
+    let known: Vec<&str> = known.iter().map(|location| location.as_str()).collect();  
+    resolve_location(query, &known)  
+}  
+
+// Distinct names in `column`, sorted; a missing or non-text column is an error rather than a panic  
+pub fn known_locations(df: &DataFrame, column: &str) -> Result<Vec<String>, String> {  
+    let names = df  
+        .column(column)  
+        .and_then(|series| series.utf8())  
+        .map_err(|e| format!("Cannot read locations from column {}: {}", column, e))?;  
+
+    let mut known: Vec<String> = names.into_iter().flatten().map(|name| name.to_string()).collect();  
+    known.sort();  
+    known.dedup();  
+    Ok(known)  
+}  
+
+pub fn resolve_location(query: &str, known: &[&str]) -> Result<String, String> {  
+    if known.contains(&query) {  
+        return Ok(query.to_string());  
+    }  
+
+    // "sydney airport", "Sydney_Airport" and "SYDNEYAIRPORT" all resolve to "SydneyAirport"  
+    let normalized_query = normalize(query);  
+    let matches: Vec<&str> = known  
+        .iter()  
+        .copied()  
+        .filter(|location| normalize(location) == normalized_query)  
+        .collect();  
+
+    if matches.len() == 1 {  
+        return Ok(matches[0].to_string());  
+    }  
+
+    let suggestions = suggest_locations(query, known);  
+    if suggestions.is_empty() {  
+        Err(format!("Unknown location: {}.", query))  
+    } else {  
+        Err(format!(  
+            "Unknown location: {}. Did you mean: {}?",  
+            query,  
+            suggestions.join(", ")  
+        ))  
+    }  
+}  
+
+pub fn suggest_locations(query: &str, known: &[&str]) -> Vec<String> {  
+    let normalized_query = normalize(query);  
+    let max_distance = (normalized_query.chars().count() / 3).max(2);  
+
+    let mut candidates: Vec<(usize, &str)> = known  
+        .iter()  
+        .map(|location| {  
+            let normalized = normalize(location);  
+            // Prefix matches such as "melbourne" -> "MelbourneAirport" count as close  
+            let distance = if !normalized_query.is_empty() && normalized.starts_with(&normalized_query) {  
+                1  
+            } else {  
+                edit_distance(&normalized_query, &normalized)  
+            };  
+            (distance, *location)  
+        })  
+        .filter(|(distance, _)| *distance <= max_distance)  
+        .collect();  
+
+    candidates.sort();  
+    candidates  
+        .into_iter()  
+        .take(MAX_SUGGESTIONS)  
+        .map(|(_, location)| location.to_string())  
+        .collect()  
+}  
+
+pub fn edit_distance(a: &str, b: &str) -> usize {  
+    let b_chars: Vec<char> = b.chars().collect();  
+    let mut previous: Vec<usize> = (0..=b_chars.len()).collect();  
+
+    for (i, a_char) in a.chars().enumerate() {  
+        let mut current = vec![i + 1; b_chars.len() + 1];  
+        for (j, b_char) in b_chars.iter().enumerate() {  
+            let substitution = previous[j] + if a_char == *b_char { 0 } else { 1 };  
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);  
+        }  
+        previous = current;  
+    }  
+
+    previous[b_chars.len()]  
+}  
+
+fn normalize(location: &str) -> String {  
+    location  
+        .chars()  
+        .filter(|c| c.is_alphanumeric())  
+        .flat_map(|c| c.to_lowercase())  
+        .collect()  
+}  
+
+#[cfg(test)]  
+mod tests {  
+    use super::*;  
+
+    const KNOWN: [&str; 4] = ["Sydney", "SydneyAirport", "Melbourne", "MelbourneAirport"];  
+
+    #[test]  
+    fn edit_distance_counts_insertions_deletions_and_substitutions() {  
+        assert_eq!(edit_distance("", ""), 0);  
+        assert_eq!(edit_distance("sydney", "sydney"), 0);  
+        assert_eq!(edit_distance("sydny", "sydney"), 1);  
+        assert_eq!(edit_distance("sydneyy", "sydney"), 1);  
+        assert_eq!(edit_distance("sidney", "sydney"), 1);  
+        assert_eq!(edit_distance("kitten", "sitting"), 3);  
+        assert_eq!(edit_distance("", "perth"), 5);  
+    }  
+
+    #[test]  
+    fn resolve_location_ignores_case_spacing_and_punctuation() {  
+        assert_eq!(resolve_location("Sydney", &KNOWN), Ok("Sydney".to_string()));  
+        assert_eq!(resolve_location("sydney airport", &KNOWN), Ok("SydneyAirport".to_string()));  
+        assert_eq!(resolve_location("Sydney_Airport", &KNOWN), Ok("SydneyAirport".to_string()));  
+        assert_eq!(resolve_location("MELBOURNE", &KNOWN), Ok("Melbourne".to_string()));  
+    }  
+
+    #[test]  
+    fn resolve_location_suggests_close_names_on_a_typo() {  
+        let error = resolve_location("Sydny", &KNOWN).unwrap_err();  
+        assert!(error.starts_with("Unknown location: Sydny. Did you mean: Sydney"), "{}", error);  
+
+        let error = resolve_location("Darwin", &KNOWN).unwrap_err();  
+        assert_eq!(error, "Unknown location: Darwin.");  
+    }  
+
+    #[test]  
+    fn known_locations_are_distinct_and_sorted() {  
+        let df = DataFrame::new(vec![Series::new(  
+            "Location",  
+            &[Some("Sydney"), None, Some("Albury"), Some("Sydney")],  
+        )])  
+        .unwrap();  
+
+        assert_eq!(known_locations(&df, "Location"), Ok(vec!["Albury".to_string(), "Sydney".to_string()]));  
+        assert_eq!(resolve_location_in(&df, "Location", "albury"), Ok("Albury".to_string()));  
+    }  
+
+    #[test]  
+    fn unreadable_location_column_is_an_error() {  
+        let df = DataFrame::new(vec![Series::new("Location", &[1i64, 2])]).unwrap();  
+
+        assert!(known_locations(&df, "City").unwrap_err().contains("City"));  
+        assert!(known_locations(&df, "Location").is_err());  
+        assert!(resolve_location_in(&df, "Location", "Sydney").is_err());  
+    }  
+
+    #[test]  
+    fn suggest_locations_ranks_closest_first_and_counts_prefixes_as_close() {  
+        assert_eq!(suggest_locations("melbourne", &KNOWN), vec!["Melbourne", "MelbourneAirport"]);  
+        assert_eq!(suggest_locations("Sydnee", &KNOWN), vec!["Sydney"]);  
+        assert!(suggest_locations("Hobart", &KNOWN).is_empty());  
+    }  
+}
\ No newline at end of file
@@ -0,0 +1,74 @@
+// src/fixtures.rs  
+
+// Recorded provider responses and the routes that replay them  
+
+use crate::{Response, Route};  
+
+pub const OWM_WEATHER: &str = include_str!("../fixtures/owm_weather.json");  
+pub const OWM_WEATHER_MISSING_FIELDS: &str = include_str!("../fixtures/owm_weather_missing_fields.json");  
+pub const OWM_FORECAST: &str = include_str!("../fixtures/owm_forecast.json");  
+pub const OWM_UNAUTHORIZED: &str = include_str!("../fixtures/owm_unauthorized.json");  
+pub const OWM_NOT_FOUND: &str = include_str!("../fixtures/owm_not_found.json");  
+
+// A 12-month forecast that matches the pipeline2 prediction schema  
+pub const CHAT_COMPLETION: &str = include_str!("../fixtures/chat_completion.json");  
+// The legacy "Key: v1, v2" text, which fails schema validation  
+pub const CHAT_COMPLETION_INVALID: &str = include_str!("../fixtures/chat_completion_invalid.json");  
+pub const CHAT_COMPLETION_MISSING_CONTENT: &str = include_str!("../fixtures/chat_completion_missing_content.json");  
+pub const CHAT_COMPLETION_RATE_LIMITED: &str = include_str!("../fixtures/chat_completion_rate_limited.json");  
+
+// Truncated mid-object, as when a proxy cuts the connection  
+pub const MALFORMED_JSON: &str = r#"{"coord":{"lon":151.2073,"lat":-33.86"#;  
+
+pub const CHAT_COMPLETIONS_PATH: &str = "/v1/chat/completions";  
+
+pub const SCENARIOS: [&str; 9] = [  
+    "ok",  
+    "unauthorized",  
+    "not_found",  
+    "malformed_json",  
+    "missing_fields",  
+    "llm_invalid_then_valid",  
+    "llm_invalid",  
+    "llm_missing_content",  
+    "llm_rate_limited",  
+];  
+
+// Routes for a named scenario; OpenWeatherMap paths are relative to the base URL,  
+// so the stub serves both providers from one address  
+pub fn scenario(name: &str) -> Option<Vec<Route>> {  
+    let owm = |weather: Response, forecast: Response| {  
+        vec![Route::new("/weather", weather), Route::new("/forecast", forecast)]  
+    };  
+    let chat = |responses: Vec<Response>| Route::sequence(CHAT_COMPLETIONS_PATH, responses);  
+
+    let routes = match name {  
+        "ok" => {  
+            let mut routes = owm(Response::new(200, OWM_WEATHER), Response::new(200, OWM_FORECAST));  
+            routes.push(chat(vec![Response::new(200, CHAT_COMPLETION)]));  
+            routes  
+        }  
+        "unauthorized" => owm(Response::new(401, OWM_UNAUTHORIZED), Response::new(401, OWM_UNAUTHORIZED)),  
+        "not_found" => owm(Response::new(404, OWM_NOT_FOUND), Response::new(404, OWM_NOT_FOUND)),  
+        "malformed_json" => {  
+            let mut routes = owm(Response::new(200, MALFORMED_JSON), Response::new(200, MALFORMED_JSON));  
+            routes.push(chat(vec![Response::new(200, MALFORMED_JSON)]));  
+            routes  
+        }  
+        "missing_fields" => owm(  
+            Response::new(200, OWM_WEATHER_MISSING_FIELDS),  
+            Response::new(200, r#"{"cod":"200","cnt":0,"city":{"name":"Sydney"}}"#),  
+        ),  
+        // Exercises the pipeline2 repair prompt: the first reply is rejected, the second accepted  
+        "llm_invalid_then_valid" => vec![chat(vec![  
+            Response::new(200, CHAT_COMPLETION_INVALID),  
+            Response::new(200, CHAT_COMPLETION),  
+        ])],  
+        "llm_invalid" => vec![chat(vec![Response::new(200, CHAT_COMPLETION_INVALID)])],  
+        "llm_missing_content" => vec![chat(vec![Response::new(200, CHAT_COMPLETION_MISSING_CONTENT)])],  
+        "llm_rate_limited" => vec![chat(vec![Response::new(429, CHAT_COMPLETION_RATE_LIMITED)])],  
+        _ => return None,  
+    };  
+
+    Some(routes)  
+}
\ No newline at end of file
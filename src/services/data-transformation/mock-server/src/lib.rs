@@ -0,0 +1,279 @@
+// src/lib.rs  
+
+// Local HTTP stub that replays recorded OpenWeatherMap and chat-completion responses.  
+// Point OPENWEATHERMAP_BASE_URL (pipeline3) or LLM_API_URL (pipeline1/pipeline2) at  
+// `MockServer::url` to exercise the network paths offline.  
+
+pub mod fixtures;  
+
+use std::io::{self, BufRead, BufReader, Read, Write};  
+use std::net::{SocketAddr, TcpListener, TcpStream};  
+use std::sync::atomic::{AtomicBool, Ordering};  
+use std::sync::{Arc, Mutex};  
+use std::thread::{self, JoinHandle};  
+use std::time::Duration;  
+
+// Connections are served one at a time, so a client that stalls mid-request is cut  
+// off after this long rather than holding up every later request and the shutdown  
+const IO_TIMEOUT: Duration = Duration::from_secs(5);  
+
+#[derive(Debug, Clone)]  
+pub struct Response {  
+    pub status: u16,  
+    pub body: String,  
+}  
+
+impl Response {  
+    pub fn new(status: u16, body: &str) -> Response {  
+        Response {  
+            status,  
+            body: body.to_string(),  
+        }  
+    }  
+}  
+
+// Requests whose path starts with `path` get the responses in order; the last  
+// one is repeated once the others are used up  
+#[derive(Debug, Clone)]  
+pub struct Route {  
+    pub path: String,  
+    pub responses: Vec<Response>,  
+}  
+
+impl Route {  
+    pub fn new(path: &str, response: Response) -> Route {  
+        Route::sequence(path, vec![response])  
+    }  
+
+    pub fn sequence(path: &str, responses: Vec<Response>) -> Route {  
+        Route {  
+            path: path.to_string(),  
+            responses,  
+        }  
+    }  
+}  
+
+pub struct MockServer {  
+    address: SocketAddr,  
+    requests: Arc<Mutex<Vec<String>>>,  
+    shutdown: Arc<AtomicBool>,  
+    handle: Option<JoinHandle<()>>,  
+}  
+
+impl MockServer {  
+    // Binds an ephemeral port on localhost and serves `routes` until dropped  
+    pub fn start(routes: Vec<Route>) -> io::Result<MockServer> {  
+        MockServer::bind("127.0.0.1:0", routes)  
+    }  
+
+    pub fn bind(address: &str, routes: Vec<Route>) -> io::Result<MockServer> {  
+        let listener = TcpListener::bind(address)?;  
+        let address = listener.local_addr()?;  
+        let requests = Arc::new(Mutex::new(vec![]));  
+        let shutdown = Arc::new(AtomicBool::new(false));  
+
+        let handle = {  
+            let requests = Arc::clone(&requests);  
+            let shutdown = Arc::clone(&shutdown);  
+            thread::spawn(move || {  
+                let mut served = vec![0; routes.len()];  
+                for stream in listener.incoming() {  
+                    if shutdown.load(Ordering::SeqCst) {  
+                        break;  
+                    }  
+                    if let Ok(stream) = stream {  
+                        // A client that hangs up or stalls mid-request only affects its own exchange  
+                        let _ = handle_connection(stream, &routes, &mut served, &requests);  
+                    }  
+                }  
+            })  
+        };  
+
+        Ok(MockServer {  
+            address,  
+            requests,  
+            shutdown,  
+            handle: Some(handle),  
+        })  
+    }  
+
+    pub fn url(&self) -> String {  
+        format!("http://{}", self.address)  
+    }  
+
+    // Request lines received so far, e.g. "GET /weather?q=Sydney,au&units=metric HTTP/1.1"  
+    pub fn requests(&self) -> Vec<String> {  
+        self.requests.lock().unwrap().clone()  
+    }  
+
+    // Blocks until the server is stopped from another thread or the process exits  
+    pub fn wait(mut self) {  
+        if let Some(handle) = self.handle.take() {  
+            let _ = handle.join();  
+        }  
+    }  
+}  
+
+impl Drop for MockServer {  
+    fn drop(&mut self) {  
+        self.shutdown.store(true, Ordering::SeqCst);  
+        // Wake the accept loop so that it sees the flag  
+        let _ = TcpStream::connect(self.address);  
+        if let Some(handle) = self.handle.take() {  
+            let _ = handle.join();  
+        }  
+    }  
+}  
+
+fn handle_connection(  
+    stream: TcpStream,  
+    routes: &[Route],  
+    served: &mut [usize],  
+    requests: &Mutex<Vec<String>>,  
+) -> io::Result<()> {  
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;  
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;  
+    let mut reader = BufReader::new(stream.try_clone()?);  
+
+    let mut request_line = String::new();  
+    reader.read_line(&mut request_line)?;  
+    let request_line = request_line.trim_end().to_string();  
+
+    // Headers are only needed to drain the body so that the client sees a clean response  
+    let mut content_length = 0;  
+    loop {  
+        let mut header = String::new();  
+        if reader.read_line(&mut header)? == 0 || header.trim_end().is_empty() {  
+            break;  
+        }  
+        if let Some((name, value)) = header.split_once(':') {  
+            if name.trim().eq_ignore_ascii_case("content-length") {  
+                content_length = value.trim().parse().unwrap_or(0);  
+            }  
+        }  
+    }  
+    let mut body = vec![0; content_length];  
+    reader.read_exact(&mut body)?;  
+
+    let target = request_line.split_whitespace().nth(1).unwrap_or("/");  
+    let path = target.split('?').next().unwrap_or("/");  
+
+    let response = match routes.iter().position(|route| path.starts_with(&route.path)) {  
+        Some(i) if !routes[i].responses.is_empty() => {  
+            let responses = &routes[i].responses;  
+            let response = responses[served[i].min(responses.len() - 1)].clone();  
+            served[i] += 1;  
+            response  
+        }  
+        _ => Response::new(404, r#"{"cod":"404","message":"no stub route for this path"}"#),  
+    };  
+
+    requests.lock().unwrap().push(request_line);  
+
+    write_response(stream, &response)  
+}  
+
+fn write_response(mut stream: TcpStream, response: &Response) -> io::Result<()> {  
+    write!(  
+        stream,  
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",  
+        response.status,  
+        reason_phrase(response.status),  
+        response.body.len(),  
+        response.body  
+    )?;  
+    stream.flush()  
+}  
+
+fn reason_phrase(status: u16) -> &'static str {  
+    match status {  
+        200 => "OK",  
+        400 => "Bad Request",  
+        401 => "Unauthorized",  
+        404 => "Not Found",  
+        429 => "Too Many Requests",  
+        500 => "Internal Server Error",  
+        502 => "Bad Gateway",  
+        503 => "Service Unavailable",  
+        _ => "Unknown",  
+    }  
+}  
+
+#[cfg(test)]  
+mod tests {  
+    use super::*;  
+    use std::time::Instant;  
+
+    // Sends one raw HTTP request and returns the status code and body  
+    fn send(server: &MockServer, request: &str) -> (u16, String) {  
+        let mut stream = TcpStream::connect(server.address).unwrap();  
+        stream.write_all(request.as_bytes()).unwrap();  
+
+        let mut response = String::new();  
+        stream.read_to_string(&mut response).unwrap();  
+        let status = response.split_whitespace().nth(1).unwrap().parse().unwrap();  
+        let body = response.split("\r\n\r\n").nth(1).unwrap_or("").to_string();  
+        (status, body)  
+    }  
+
+    fn get(server: &MockServer, target: &str) -> (u16, String) {  
+        send(server, &format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", target))  
+    }  
+
+    #[test]  
+    fn sequence_is_replayed_in_order_then_repeats_its_last_response() {  
+        let server = MockServer::start(vec![Route::sequence(  
+            "/chat",  
+            vec![Response::new(429, "first"), Response::new(200, "second")],  
+        )])  
+        .unwrap();  
+
+        assert_eq!(get(&server, "/chat"), (429, "first".to_string()));  
+        assert_eq!(get(&server, "/chat"), (200, "second".to_string()));  
+        assert_eq!(get(&server, "/chat"), (200, "second".to_string()));  
+    }  
+
+    #[test]  
+    fn paths_match_by_prefix_and_ignore_the_query() {  
+        let server = MockServer::start(vec![Route::new("/weather", Response::new(200, "{}"))]).unwrap();  
+
+        assert_eq!(get(&server, "/weather?q=Sydney,au").0, 200);  
+        assert_eq!(get(&server, "/forecast?q=Sydney,au").0, 404);  
+        assert_eq!(  
+            server.requests(),  
+            vec![  
+                "GET /weather?q=Sydney,au HTTP/1.1".to_string(),  
+                "GET /forecast?q=Sydney,au HTTP/1.1".to_string(),  
+            ]  
+        );  
+    }  
+
+    #[test]  
+    fn request_body_is_drained_before_responding() {  
+        let server = MockServer::start(vec![Route::new("/chat", Response::new(200, "ok"))]).unwrap();  
+
+        let body = r#"{"messages":[]}"#;  
+        let request = format!("POST /chat HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}", body.len(), body);  
+        assert_eq!(send(&server, &request), (200, "ok".to_string()));  
+        assert_eq!(server.requests(), vec!["POST /chat HTTP/1.1".to_string()]);  
+    }  
+
+    #[test]  
+    fn stalled_client_is_cut_off() {  
+        let server = MockServer::start(vec![Route::new("/weather", Response::new(200, "{}"))]).unwrap();  
+
+        // Connects but never sends a request line  
+        let _stalled = TcpStream::connect(server.address).unwrap();  
+        let started = Instant::now();  
+        assert_eq!(get(&server, "/weather").0, 200);  
+        assert!(started.elapsed() < IO_TIMEOUT * 2);  
+    }  
+
+    #[test]  
+    fn every_listed_scenario_has_routes() {  
+        for name in fixtures::SCENARIOS.iter() {  
+            assert!(!fixtures::scenario(name).unwrap().is_empty(), "{}", name);  
+        }  
+        assert!(fixtures::scenario("unknown").is_none());  
+    }  
+}
\ No newline at end of file
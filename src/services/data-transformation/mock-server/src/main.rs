@@ -0,0 +1,36 @@
+// src/main.rs  
+
+// Runs the stub on its own, e.g. `mock-server ok 8089`, then  
+//   OPENWEATHERMAP_BASE_URL=http://127.0.0.1:8089  
+//   LLM_API_URL=http://127.0.0.1:8089/v1/chat/completions  
+
+use std::env;  
+use std::process;  
+
+use mock_server::fixtures;  
+use mock_server::MockServer;  
+
+fn main() {  
+    let args: Vec<String> = env::args().collect();  
+    let name = args.get(1).map(|s| s.as_str()).unwrap_or("ok");  
+    let port = args.get(2).map(|s| s.as_str()).unwrap_or("0");  
+
+    let routes = match fixtures::scenario(name) {  
+        Some(routes) => routes,  
+        None => {  
+            eprintln!("Unknown scenario: {}. Available: {}", name, fixtures::SCENARIOS.join(", "));  
+            process::exit(2);  
+        }  
+    };  
+
+    let server = match MockServer::bind(&format!("127.0.0.1:{}", port), routes) {  
+        Ok(server) => server,  
+        Err(e) => {  
+            eprintln!("Failed to start mock server: {}", e);  
+            process::exit(1);  
+        }  
+    };  
+
+    println!("Serving scenario '{}' at {}", name, server.url());  
+    server.wait();  
+}
\ No newline at end of file
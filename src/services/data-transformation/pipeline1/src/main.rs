@@ -2,16 +2,15 @@
 
 use marine_rs_sdk::marine;  
 use marine_rs_sdk::module_manifest;  
-use marine_rs_sdk::MountedBinaryResult;  
 
 module_manifest!();  
 
 pub fn main() {}  
 
 use serde::{Deserialize, Serialize};  
+use std::collections::BTreeMap;  
 use std::env;  
 use std::fs;  
-use std::path::PathBuf;  
 
 use chrono::{Datelike, NaiveDate};  
 use polars::prelude::*;  
@@ -23,104 +22,1024 @@ use tokio::runtime::Runtime;
 #[derive(Debug, Serialize, Deserialize)]  
 pub struct WeatherReport {  
     report: String,  
+    narrative_source: String,  
+    llm_error: String,  
     min_temp: Vec<Option<f32>>,  
     max_temp: Vec<Option<f32>>,  
     wind_direction: String,  
     wind_speed: Vec<Option<f32>>,  
     rainfall: Vec<Option<f32>>,  
+    statistics: Vec<MetricSummary>,  
+    anomalies: Vec<MetricAnomaly>,  
+    wind_roses: Vec<WindRose>,  
+    events: Vec<WeatherEvent>,  
+    days: Vec<DailyWeather>,  
 }  
 
 #[marine]  
-pub fn generate_weather_report(city: String, year: i32, month: u32) -> WeatherReport {  
+#[derive(Debug, Serialize, Deserialize)]  
+pub struct DailyWeather {  
+    date: String,  
+    min_temp: Option<f32>,  
+    max_temp: Option<f32>,  
+    wind_direction: String,  
+    wind_speed: Option<f32>,  
+    rainfall: Option<f32>,  
+}  
+
+#[marine]  
+#[derive(Debug, Serialize, Deserialize)]  
+pub struct WeatherEvent {  
+    event_type: String,  
+    start_date: String,  
+    end_date: String,  
+    duration_days: u32,  
+    // The most extreme value in the event: the highest, or the lowest rainfall for a dry spell  
+    peak_value: f32,  
+}  
+
+#[marine]  
+#[derive(Debug, Clone, Serialize, Deserialize)]  
+pub struct EventThresholds {  
+    heatwave_max_temp: f32,  
+    heatwave_min_days: u32,  
+    heavy_rain_mm: f32,  
+    frost_min_temp: f32,  
+    damaging_gust_speed: f32,  
+    dry_day_max_rain_mm: f32,  
+    dry_spell_min_days: u32,  
+}  
+
+#[marine]  
+#[derive(Debug, Serialize, Deserialize)]  
+pub struct WeatherEventsReport {  
+    error: String,  
+    thresholds: EventThresholds,  
+    events: Vec<WeatherEvent>,  
+}  
+
+#[marine]  
+#[derive(Debug, Serialize, Deserialize)]  
+pub struct WindRoseBin {  
+    direction: String,  
+    count: u32,  
+    frequency: f32,  
+    mean_speed: Option<f32>,  
+}  
+
+#[marine]  
+#[derive(Debug, Serialize, Deserialize)]  
+pub struct WindRose {  
+    direction_column: String,  
+    speed_column: String,  
+    observations: u32,  
+    missing: u32,  
+    bins: Vec<WindRoseBin>,  
+}  
+
+#[marine]  
+#[derive(Debug, Serialize, Deserialize)]  
+pub struct MetricSummary {  
+    metric: String,  
+    mean: Option<f32>,  
+    min: Option<f32>,  
+    max: Option<f32>,  
+    std_dev: Option<f32>,  
+    median: Option<f32>,  
+    p10: Option<f32>,  
+    p90: Option<f32>,  
+    null_count: u32,  
+    observation_count: u32,  
+}  
+
+#[marine]  
+#[derive(Debug, Serialize, Deserialize)]  
+pub struct MetricAnomaly {  
+    metric: String,  
+    value: Option<f32>,  
+    normal: Option<f32>,  
+    anomaly: Option<f32>,  
+    percentile: Option<f32>,  
+    baseline_years: u32,  
+    baseline_start_year: i32,  
+    baseline_end_year: i32,  
+    description: String,  
+}  
+
+#[marine]  
+#[derive(Debug, Serialize, Deserialize)]  
+pub struct LocationCoverage {  
+    location: String,  
+    first_date: String,  
+    last_date: String,  
+    row_count: u32,  
+}  
+
+#[marine]  
+#[derive(Debug, Serialize, Deserialize)]  
+pub struct LocationList {  
+    error: String,  
+    locations: Vec<LocationCoverage>,  
+}  
+
+#[marine]  
+#[derive(Debug, Serialize, Deserialize)]  
+pub struct MetricSeries {  
+    metric: String,  
+    // Empty unless this metric could not be read; the other series are still returned  
+    error: String,  
+    kind: String,  
+    values: Vec<Option<f32>>,  
+    labels: Vec<String>,  
+}  
+
+#[marine]  
+#[derive(Debug, Serialize, Deserialize)]  
+pub struct MetricSeriesReport {  
+    error: String,  
+    dates: Vec<String>,  
+    series: Vec<MetricSeries>,  
+}  
+
+#[marine]  
+#[derive(Debug, Serialize, Deserialize)]  
+pub struct CityWeatherSeries {  
+    city: String,  
+    error: String,  
+    min_temp: Vec<Option<f32>>,  
+    max_temp: Vec<Option<f32>>,  
+    wind_direction: String,  
+    wind_speed: Vec<Option<f32>>,  
+    rainfall: Vec<Option<f32>>,  
+    mean_max_temp: Option<f32>,  
+    total_rainfall: Option<f32>,  
+    mean_wind_speed: Option<f32>,  
+    mean_diurnal_range: Option<f32>,  
+    days: Vec<DailyWeather>,  
+}  
+
+#[marine]  
+#[derive(Debug, Serialize, Deserialize)]  
+pub struct CityRanking {  
+    city: String,  
+    value: Option<f32>,  
+}  
+
+#[marine]  
+#[derive(Debug, Serialize, Deserialize)]  
+pub struct ComparisonSummary {  
+    warmest: CityRanking,  
+    wettest: CityRanking,  
+    windiest: CityRanking,  
+    largest_diurnal_range: CityRanking,  
+}  
+
+#[marine]  
+#[derive(Debug, Serialize, Deserialize)]  
+pub struct ComparativeWeatherReport {  
+    report: String,  
+    narrative_source: String,  
+    llm_error: String,  
+    cities: Vec<CityWeatherSeries>,  
+    summary: ComparisonSummary,  
+}  
+
+const COMPASS_POINTS: [&str; 16] = [  
+    "N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE", "S", "SSW", "SW", "WSW", "W", "WNW", "NW", "NNW",  
+];  
+
+// Yes/No columns, exposed as 1.0/0.0  
+const BOOLEAN_METRICS: [&str; 2] = ["RainToday", "RainTomorrow"];  
+
+const DEFAULT_LLM_API_URL: &str = "https://api.openai.com/v1/chat/completions";  
+
+#[marine]  
+pub fn list_locations() -> LocationList {  
     let file_path = "./weatherAUS.csv";  
-    let system_prompt_path = "./system_prompt.txt";  
-    let example_input_path = "./example_input1.txt";  
-    let example_output_path = "./example_output1.txt";  
 
-    // Load data  
     let df = match load_weather_data(file_path) {  
         Ok(df) => df,  
         Err(e) => {  
-            return WeatherReport {  
-                report: format!("Error loading data: {}", e),  
-                min_temp: vec![],  
-                max_temp: vec![],  
-                wind_direction: "".to_string(),  
-                wind_speed: vec![],  
-                rainfall: vec![],  
+            return LocationList {  
+                error: format!("Error loading data: {}", e),  
+                locations: vec![],  
+            }  
+        }  
+    };  
+
+    let dates = get_dates(&df);  
+    let location_series = df.column("Location").unwrap();  
+
+    // (first date, last date, row count) per location  
+    let mut coverage: BTreeMap<&str, (Option<NaiveDate>, Option<NaiveDate>, u32)> = BTreeMap::new();  
+    for (date_opt, loc_opt) in dates.iter().zip(location_series.utf8().unwrap()) {  
+        if let Some(loc) = loc_opt {  
+            let entry = coverage.entry(loc).or_insert((None, None, 0));  
+            if let Some(date) = date_opt {  
+                entry.0 = Some(entry.0.map_or(*date, |first| first.min(*date)));  
+                entry.1 = Some(entry.1.map_or(*date, |last| last.max(*date)));  
             }  
+            entry.2 += 1;  
         }  
+    }  
+
+    LocationList {  
+        error: "".to_string(),  
+        locations: coverage  
+            .into_iter()  
+            .map(|(location, (first, last, row_count))| LocationCoverage {  
+                location: location.to_string(),  
+                first_date: first.map(|d| d.to_string()).unwrap_or_default(),  
+                last_date: last.map(|d| d.to_string()).unwrap_or_default(),  
+                row_count,  
+            })  
+            .collect(),  
+    }  
+}  
+
+#[marine]  
+pub fn generate_weather_report(city: String, year: i32, month: u32, mode: String) -> WeatherReport {  
+    let file_path = "./weatherAUS.csv";  
+
+    let mode = match parse_narrative_mode(&mode) {  
+        Ok(mode) => mode,  
+        Err(e) => return empty_weather_report(e),  
+    };  
+
+    let (start, end) = match month_bounds(year, month) {  
+        Some(bounds) => bounds,  
+        None => return empty_weather_report(format!("Invalid month: {}/{}.", month, year)),  
+    };  
+
+    // Load data  
+    let df = match load_weather_data(file_path) {  
+        Ok(df) => df,  
+        Err(e) => return empty_weather_report(format!("Error loading data: {}", e)),  
+    };  
+
+    let city = match location_match::resolve_location_in(&df, "Location", &city) {  
+        Ok(city) => city,  
+        Err(e) => return empty_weather_report(e),  
     };  
 
-    // Filter data  
-    let filtered_df = get_city_month_data(df, &city, year, month);  
+    // Filter data  
+    let filtered_df = get_city_range_data(&df, &city, start, end);  
+
+    // Compare against the same calendar month in every other year  
+    let anomalies = get_monthly_anomalies(&df, &city, year, month);  
+
+    build_weather_report(  
+        &filtered_df,  
+        &city,  
+        &format!("{}/{}", month, year),  
+        start,  
+        end,  
+        anomalies,  
+        mode,  
+    )  
+}  
+
+#[marine]  
+pub fn generate_weather_report_range(  
+    city: String,  
+    start_date: String,  
+    end_date: String,  
+    mode: String,  
+) -> WeatherReport {  
+    let file_path = "./weatherAUS.csv";  
+
+    let mode = match parse_narrative_mode(&mode) {  
+        Ok(mode) => mode,  
+        Err(e) => return empty_weather_report(e),  
+    };  
+
+    let (start, end) = match parse_date_range(&start_date, &end_date) {  
+        Ok(range) => range,  
+        Err(e) => return empty_weather_report(e),  
+    };  
+
+    // Load data  
+    let df = match load_weather_data(file_path) {  
+        Ok(df) => df,  
+        Err(e) => return empty_weather_report(format!("Error loading data: {}", e)),  
+    };  
+
+    let city = match location_match::resolve_location_in(&df, "Location", &city) {  
+        Ok(city) => city,  
+        Err(e) => return empty_weather_report(e),  
+    };  
+
+    // Clamp the requested range to the dataset's coverage for the city  
+    let (first, last) = match get_city_coverage(&df, &city) {  
+        Some(coverage) => coverage,  
+        None => return empty_weather_report(format!("No data available for {}.", city)),  
+    };  
+
+    if end < first || start > last {  
+        return empty_weather_report(format!(  
+            "Requested range {} to {} is outside the data coverage for {} ({} to {}).",  
+            start, end, city, first, last  
+        ));  
+    }  
+
+    let start = start.max(first);  
+    let end = end.min(last);  
+
+    // Filter data  
+    let filtered_df = get_city_range_data(&df, &city, start, end);  
+
+    build_weather_report(  
+        &filtered_df,  
+        &city,  
+        &format!("{} to {}", start, end),  
+        start,  
+        end,  
+        vec![],  
+        mode,  
+    )  
+}  
+
+#[marine]  
+pub fn generate_comparative_weather_report(  
+    cities: Vec<String>,  
+    start_date: String,  
+    end_date: String,  
+    mode: String,  
+) -> ComparativeWeatherReport {  
+    let file_path = "./weatherAUS.csv";  
+    let system_prompt_path = "./system_prompt_comparison.txt";  
+    let example_input_path = "./example_input_comparison.txt";  
+    let example_output_path = "./example_output_comparison.txt";  
+
+    let mode = match parse_narrative_mode(&mode) {  
+        Ok(mode) => mode,  
+        Err(e) => return empty_comparative_report(e),  
+    };  
+
+    if cities.is_empty() {  
+        return empty_comparative_report("No cities requested.".to_string());  
+    }  
+
+    let (start, end) = match parse_date_range(&start_date, &end_date) {  
+        Ok(range) => range,  
+        Err(e) => return empty_comparative_report(e),  
+    };  
+
+    // Load data  
+    let df = match load_weather_data(file_path) {  
+        Ok(df) => df,  
+        Err(e) => return empty_comparative_report(format!("Error loading data: {}", e)),  
+    };  
+
+    let (cities, unresolved): (Vec<_>, Vec<_>) = cities  
+        .iter()  
+        .map(|city| location_match::resolve_location_in(&df, "Location", city))  
+        .partition(|resolved| resolved.is_ok());  
+
+    if !unresolved.is_empty() {  
+        let errors: Vec<String> = unresolved.into_iter().filter_map(|r| r.err()).collect();  
+        return empty_comparative_report(errors.join(" "));  
+    }  
+
+    let cities: Vec<String> = cities.into_iter().filter_map(|r| r.ok()).collect();  
+
+    let period = format!("{} to {}", start, end);  
+
+    // Filter data per city  
+    let city_frames: Vec<(String, DataFrame)> = cities  
+        .iter()  
+        .map(|city| (city.clone(), get_city_range_data(&df, city, start, end)))  
+        .collect();  
+
+    let series: Vec<CityWeatherSeries> = city_frames  
+        .iter()  
+        .map(|(city, city_df)| get_city_weather_series(city_df, city, &period, start, end))  
+        .collect();  
+
+    let summary = get_comparison_summary(&series);  
+
+    if series.iter().all(|s| !s.error.is_empty()) {  
+        return ComparativeWeatherReport {  
+            report: format!("No data available for any requested city in {}.", period),  
+            narrative_source: "".to_string(),  
+            llm_error: "".to_string(),  
+            cities: series,  
+            summary,  
+        };  
+    }  
+
+    // A single prompt covers every city  
+    let narrative = generate_narrative(  
+        mode,  
+        || generate_comparative_prompt(&city_frames, &summary, &period),  
+        (system_prompt_path, example_input_path, example_output_path),  
+        || generate_comparative_template_report(&series, &summary, &period),  
+    );  
+
+    ComparativeWeatherReport {  
+        report: narrative.report,  
+        narrative_source: narrative.source,  
+        llm_error: narrative.llm_error,  
+        cities: series,  
+        summary,  
+    }  
+}  
+
+fn get_city_weather_series(  
+    df: &DataFrame,  
+    city: &str,  
+    period: &str,  
+    start: NaiveDate,  
+    end: NaiveDate,  
+) -> CityWeatherSeries {  
+    if df.height() == 0 {  
+        return CityWeatherSeries {  
+            city: city.to_string(),  
+            error: format!("No data available for {} in {}.", city, period),  
+            min_temp: vec![],  
+            max_temp: vec![],  
+            wind_direction: "".to_string(),  
+            wind_speed: vec![],  
+            rainfall: vec![],  
+            mean_max_temp: None,  
+            total_rainfall: None,  
+            mean_wind_speed: None,  
+            mean_diurnal_range: None,  
+            days: vec![],  
+        };  
+    }  
+
+    CityWeatherSeries {  
+        city: city.to_string(),  
+        error: "".to_string(),  
+        min_temp: get_min_temp(df),  
+        max_temp: get_max_temp(df),  
+        wind_direction: get_wind_direction(df),  
+        wind_speed: get_wind_speed(df),  
+        rainfall: get_rainfall(df),  
+        mean_max_temp: column_mean(df, "MaxTemp"),  
+        total_rainfall: column_sum(df, "Rainfall"),  
+        mean_wind_speed: column_mean(df, "WindGustSpeed"),  
+        mean_diurnal_range: get_mean_diurnal_range(df),  
+        days: get_daily_weather(df, start, end),  
+    }  
+}  
+
+fn get_comparison_summary(series: &[CityWeatherSeries]) -> ComparisonSummary {  
+    let rank_by = |metric: fn(&CityWeatherSeries) -> Option<f32>| -> CityRanking {  
+        series  
+            .iter()  
+            .filter_map(|s| metric(s).map(|v| (s.city.clone(), v)))  
+            .fold(None, |best: Option<(String, f32)>, (city, v)| match best {  
+                Some((_, best_v)) if best_v >= v => best,  
+                _ => Some((city, v)),  
+            })  
+            .map(|(city, v)| CityRanking { city, value: Some(v) })  
+            .unwrap_or(CityRanking {  
+                city: "".to_string(),  
+                value: None,  
+            })  
+    };  
+
+    ComparisonSummary {  
+        warmest: rank_by(|s| s.mean_max_temp),  
+        wettest: rank_by(|s| s.total_rainfall),  
+        windiest: rank_by(|s| s.mean_wind_speed),  
+        largest_diurnal_range: rank_by(|s| s.mean_diurnal_range),  
+    }  
+}  
+
+fn generate_comparative_prompt(  
+    city_frames: &[(String, DataFrame)],  
+    summary: &ComparisonSummary,  
+    period: &str,  
+) -> String {  
+    let mut prompt = format!(  
+        "Below is the weather data for {} cities during {}. Compare them in a single report.\n\n",  
+        city_frames.len(),  
+        period  
+    );  
+
+    for (city, df) in city_frames {  
+        if df.height() == 0 {  
+            prompt.push_str(&format!("## {}\nNo data available.\n\n", city));  
+        } else {  
+            let csv_data = to_csv(df);  
+            prompt.push_str(&format!("## {}\n{}\n", city, csv_data));  
+        }  
+    }  
+
+    let describe = |label: &str, ranking: &CityRanking, unit: &str| -> String {  
+        match ranking.value {  
+            Some(v) => format!("{}: {} ({:.1}{})\n", label, ranking.city, v, unit),  
+            None => format!("{}: n/a\n", label),  
+        }  
+    };  
+
+    prompt.push_str("Summary:\n");  
+    prompt.push_str(&describe("Warmest (mean MaxTemp)", &summary.warmest, "°C"));  
+    prompt.push_str(&describe("Wettest (total Rainfall)", &summary.wettest, " mm"));  
+    prompt.push_str(&describe("Windiest (mean WindGustSpeed)", &summary.windiest, " km/h"));  
+    prompt.push_str(&describe(  
+        "Largest diurnal range (mean MaxTemp - MinTemp)",  
+        &summary.largest_diurnal_range,  
+        "°C",  
+    ));  
+
+    prompt  
+}  
+
+fn empty_comparative_report(report: String) -> ComparativeWeatherReport {  
+    ComparativeWeatherReport {  
+        report,  
+        narrative_source: "".to_string(),  
+        llm_error: "".to_string(),  
+        cities: vec![],  
+        summary: get_comparison_summary(&[]),  
+    }  
+}  
+
+#[marine]  
+pub fn get_weather_metrics(  
+    city: String,  
+    start_date: String,  
+    end_date: String,  
+    metrics: Vec<String>,  
+) -> MetricSeriesReport {  
+    let file_path = "./weatherAUS.csv";  
+
+    let empty_metric_report = |error: String| MetricSeriesReport {  
+        error,  
+        dates: vec![],  
+        series: vec![],  
+    };  
+
+    if metrics.is_empty() {  
+        return empty_metric_report("No metrics requested.".to_string());  
+    }  
+
+    let (start, end) = match parse_date_range(&start_date, &end_date) {  
+        Ok(range) => range,  
+        Err(e) => return empty_metric_report(e),  
+    };  
+
+    // Load data  
+    let df = match load_weather_data(file_path) {  
+        Ok(df) => df,  
+        Err(e) => return empty_metric_report(format!("Error loading data: {}", e)),  
+    };  
+
+    let city = match location_match::resolve_location_in(&df, "Location", &city) {  
+        Ok(city) => city,  
+        Err(e) => return empty_metric_report(e),  
+    };  
+
+    // Validate requested metrics against the CSV header  
+    let available: Vec<&str> = df  
+        .get_column_names()  
+        .into_iter()  
+        .filter(|name| *name != "Date" && *name != "Location")  
+        .collect();  
+    let unknown: Vec<&str> = metrics  
+        .iter()  
+        .map(|m| m.as_str())  
+        .filter(|m| !available.contains(m))  
+        .collect();  
+
+    if !unknown.is_empty() {  
+        return empty_metric_report(format!(  
+            "Unknown metrics: {}. Available metrics: {}.",  
+            unknown.join(", "),  
+            available.join(", ")  
+        ));  
+    }  
+
+    // Filter data  
+    let filtered_df = get_city_range_data(&df, &city, start, end);  
+
+    if filtered_df.height() == 0 {  
+        return empty_metric_report(format!(  
+            "No data available for {} in {} to {}.",  
+            city, start, end  
+        ));  
+    }  
+
+    MetricSeriesReport {  
+        error: "".to_string(),  
+        dates: get_dates(&filtered_df)  
+            .into_iter()  
+            .map(|date_opt| date_opt.map(|date| date.to_string()).unwrap_or_default())  
+            .collect(),  
+        series: metrics  
+            .iter()  
+            .map(|metric| get_metric_series(&filtered_df, metric))  
+            .collect(),  
+    }  
+}  
+
+#[marine]  
+pub fn default_event_thresholds() -> EventThresholds {  
+    EventThresholds {  
+        heatwave_max_temp: 35.0,  
+        heatwave_min_days: 3,  
+        heavy_rain_mm: 25.0,  
+        frost_min_temp: 0.0,  
+        damaging_gust_speed: 90.0,  
+        dry_day_max_rain_mm: 1.0,  
+        dry_spell_min_days: 10,  
+    }  
+}  
+
+#[marine]  
+pub fn detect_weather_events(  
+    city: String,  
+    start_date: String,  
+    end_date: String,  
+    thresholds: EventThresholds,  
+) -> WeatherEventsReport {  
+    let file_path = "./weatherAUS.csv";  
+
+    let empty_events_report = |error: String, thresholds: EventThresholds| WeatherEventsReport {  
+        error,  
+        thresholds,  
+        events: vec![],  
+    };  
+
+    let (start, end) = match parse_date_range(&start_date, &end_date) {  
+        Ok(range) => range,  
+        Err(e) => return empty_events_report(e, thresholds),  
+    };  
+
+    // Load data  
+    let df = match load_weather_data(file_path) {  
+        Ok(df) => df,  
+        Err(e) => return empty_events_report(format!("Error loading data: {}", e), thresholds),  
+    };  
+
+    let city = match location_match::resolve_location_in(&df, "Location", &city) {  
+        Ok(city) => city,  
+        Err(e) => return empty_events_report(e, thresholds),  
+    };  
+
+    // Filter data  
+    let filtered_df = get_city_range_data(&df, &city, start, end);  
+
+    if filtered_df.height() == 0 {  
+        return empty_events_report(  
+            format!("No data available for {} in {} to {}.", city, start, end),  
+            thresholds,  
+        );  
+    }  
+
+    WeatherEventsReport {  
+        error: "".to_string(),  
+        events: detect_events(&filtered_df, &thresholds),  
+        thresholds,  
+    }  
+}  
+
+fn build_weather_report(  
+    filtered_df: &DataFrame,  
+    city: &str,  
+    period: &str,  
+    start: NaiveDate,  
+    end: NaiveDate,  
+    anomalies: Vec<MetricAnomaly>,  
+    mode: NarrativeMode,  
+) -> WeatherReport {  
+    let system_prompt_path = "./system_prompt.txt";  
+    let example_input_path = "./example_input1.txt";  
+    let example_output_path = "./example_output1.txt";  
+
+    if filtered_df.height() == 0 {  
+        return empty_weather_report(format!("No data available for {} in {}.", city, period));  
+    }  
+
+    // Extract weather data  
+    let min_temp = get_min_temp(filtered_df);  
+    let max_temp = get_max_temp(filtered_df);  
+    let wind_direction = get_wind_direction(filtered_df);  
+    let wind_speed = get_wind_speed(filtered_df);  
+    let rainfall = get_rainfall(filtered_df);  
+    let statistics = get_statistics(filtered_df);  
+    let wind_roses = get_wind_roses(filtered_df);  
+    let days = get_daily_weather(filtered_df, start, end);  
+    let events = detect_events(filtered_df, &default_event_thresholds());  
+
+    let narrative = generate_narrative(  
+        mode,  
+        || generate_prompt(filtered_df, city, period, &anomalies, &events),  
+        (system_prompt_path, example_input_path, example_output_path),  
+        || generate_template_report(city, period, &statistics, &anomalies, &wind_roses, &events),  
+    );  
+
+    WeatherReport {  
+        report: narrative.report,  
+        narrative_source: narrative.source,  
+        llm_error: narrative.llm_error,  
+        min_temp,  
+        max_temp,  
+        wind_direction,  
+        wind_speed,  
+        rainfall,  
+        statistics,  
+        anomalies,  
+        wind_roses,  
+        events,  
+        days,  
+    }  
+}  
+
+fn empty_weather_report(report: String) -> WeatherReport {  
+    WeatherReport {  
+        report,  
+        narrative_source: "".to_string(),  
+        llm_error: "".to_string(),  
+        min_temp: vec![],  
+        max_temp: vec![],  
+        wind_direction: "".to_string(),  
+        wind_speed: vec![],  
+        rainfall: vec![],  
+        statistics: vec![],  
+        anomalies: vec![],  
+        wind_roses: vec![],  
+        events: vec![],  
+        days: vec![],  
+    }  
+}  
+
+enum NarrativeMode {  
+    Llm,  
+    Template,  
+}  
+
+struct Narrative {  
+    report: String,  
+    source: String,  
+    llm_error: String,  
+}  
+
+fn parse_narrative_mode(mode: &str) -> Result<NarrativeMode, String> {  
+    match mode.trim().to_lowercase().as_str() {  
+        "" | "llm" => Ok(NarrativeMode::Llm),  
+        "template" => Ok(NarrativeMode::Template),  
+        other => Err(format!("Unknown mode: {}. Expected \"llm\" or \"template\".", other)),  
+    }  
+}  
 
-    if filtered_df.height() == 0 {  
-        return WeatherReport {  
-            report: format!("No data available for {} in {}/{}.", city, month, year),  
-            min_temp: vec![],  
-            max_temp: vec![],  
-            wind_direction: "".to_string(),  
-            wind_speed: vec![],  
-            rainfall: vec![],  
+// Falls back to the template narrative whenever the LLM is disabled or unreachable  
+fn generate_narrative(  
+    mode: NarrativeMode,  
+    prompt: impl FnOnce() -> String,  
+    (system_prompt_path, example_input_path, example_output_path): (&str, &str, &str),  
+    template: impl FnOnce() -> String,  
+) -> Narrative {  
+    if let NarrativeMode::Template = mode {  
+        return Narrative {  
+            report: template(),  
+            source: "template".to_string(),  
+            llm_error: "".to_string(),  
         };  
     }  
 
-    // Generate prompt  
-    let prompt = generate_prompt(&filtered_df, &city, year, month);  
-
-    // Call OpenAI API  
-    let report = match call_openai_api(  
-        &prompt,  
+    match call_openai_api(  
+        &prompt(),  
         system_prompt_path,  
         example_input_path,  
         example_output_path,  
     ) {  
-        Ok(r) => r,  
-        Err(e) => format!("Error in API call: {}", e),  
-    };  
+        Ok(report) => Narrative {  
+            report,  
+            source: "llm".to_string(),  
+            llm_error: "".to_string(),  
+        },  
+        Err(e) => Narrative {  
+            report: template(),  
+            source: "template_fallback".to_string(),  
+            llm_error: e.to_string(),  
+        },  
+    }  
+}  
 
-    // Extract weather data  
-    let min_temp = get_min_temp(&filtered_df);  
-    let max_temp = get_max_temp(&filtered_df);  
-    let wind_direction = get_wind_direction(&filtered_df);  
-    let wind_speed = get_wind_speed(&filtered_df);  
-    let rainfall = get_rainfall(&filtered_df);  
+fn generate_template_report(  
+    city: &str,  
+    period: &str,  
+    statistics: &[MetricSummary],  
+    anomalies: &[MetricAnomaly],  
+    wind_roses: &[WindRose],  
+    events: &[WeatherEvent],  
+) -> String {  
+    let stat = |metric: &str| statistics.iter().find(|s| s.metric == metric);  
+    let mut sentences = vec![format!("Weather report for {}, {}.", city, period)];  
 
-    WeatherReport {  
-        report,  
-        min_temp,  
-        max_temp,  
-        wind_direction,  
-        wind_speed,  
-        rainfall,  
+    if let (Some(min_temp), Some(max_temp)) = (stat("MinTemp"), stat("MaxTemp")) {  
+        if let (Some(low), Some(high), Some(mean_min), Some(mean_max)) =  
+            (min_temp.min, max_temp.max, min_temp.mean, max_temp.mean)  
+        {  
+            sentences.push(format!(  
+                "Temperatures ranged from {:.1}°C to {:.1}°C, with an average daily minimum of {:.1}°C and an average daily maximum of {:.1}°C.",  
+                low, high, mean_min, mean_max  
+            ));  
+        }  
+    }  
+
+    if let Some(rainfall) = stat("Rainfall") {  
+        if let (Some(mean), Some(max)) = (rainfall.mean, rainfall.max) {  
+            let total = mean * rainfall.observation_count as f32;  
+            if total > 0.0 {  
+                sentences.push(format!(  
+                    "Total rainfall was {:.1} mm across {} recorded days, with the wettest day reaching {:.1} mm.",  
+                    total, rainfall.observation_count, max  
+                ));  
+            } else {  
+                sentences.push(format!(  
+                    "No rainfall was recorded across {} days.",  
+                    rainfall.observation_count  
+                ));  
+            }  
+        }  
+    }  
+
+    if let Some(wind_speed) = stat("WindGustSpeed") {  
+        if let (Some(mean), Some(max)) = (wind_speed.mean, wind_speed.max) {  
+            let prevailing = wind_roses  
+                .iter()  
+                .find(|rose| rose.direction_column == "WindGustDir")  
+                .and_then(|rose| rose.bins.iter().filter(|bin| bin.count > 0).max_by_key(|bin| bin.count));  
+            match prevailing {  
+                Some(bin) => sentences.push(format!(  
+                    "Wind gusts averaged {:.1} km/h and peaked at {:.1} km/h, most often from the {} ({:.0}% of observations).",  
+                    mean,  
+                    max,  
+                    bin.direction,  
+                    bin.frequency * 100.0  
+                )),  
+                None => sentences.push(format!(  
+                    "Wind gusts averaged {:.1} km/h and peaked at {:.1} km/h.",  
+                    mean, max  
+                )),  
+            }  
+        }  
     }  
+
+    for anomaly in anomalies.iter().filter(|a| a.anomaly.is_some()) {  
+        sentences.push(format!("{} was {}.", anomaly.metric, anomaly.description));  
+    }  
+
+    if events.is_empty() {  
+        sentences.push("No extreme events were detected.".to_string());  
+    } else {  
+        let described: Vec<String> = events  
+            .iter()  
+            .map(|event| {  
+                if event.duration_days > 1 {  
+                    format!(  
+                        "{} from {} to {} (peak {:.1})",  
+                        event.event_type.replace('_', " "),  
+                        event.start_date,  
+                        event.end_date,  
+                        event.peak_value  
+                    )  
+                } else {  
+                    format!(  
+                        "{} on {} ({:.1})",  
+                        event.event_type.replace('_', " "),  
+                        event.start_date,  
+                        event.peak_value  
+                    )  
+                }  
+            })  
+            .collect();  
+        sentences.push(format!("Notable events: {}.", described.join("; ")));  
+    }  
+
+    sentences.join(" ")  
+}  
+
+fn generate_comparative_template_report(  
+    series: &[CityWeatherSeries],  
+    summary: &ComparisonSummary,  
+    period: &str,  
+) -> String {  
+    let mut sentences = vec![format!(  
+        "Weather comparison of {} cities for {}.",  
+        series.len(),  
+        period  
+    )];  
+
+    let rankings = [  
+        ("warmest", &summary.warmest, "°C mean maximum"),  
+        ("wettest", &summary.wettest, " mm total rainfall"),  
+        ("windiest", &summary.windiest, " km/h mean gust"),  
+        ("largest diurnal range", &summary.largest_diurnal_range, "°C mean range"),  
+    ];  
+    for (label, ranking, unit) in rankings.iter() {  
+        if let Some(value) = ranking.value {  
+            sentences.push(format!(  
+                "{} had the {} ({:.1}{}).",  
+                ranking.city, label, value, unit  
+            ));  
+        }  
+    }  
+
+    for city in series {  
+        if !city.error.is_empty() {  
+            sentences.push(format!("{}: no data.", city.city));  
+            continue;  
+        }  
+        let describe = |value: Option<f32>, unit: &str| {  
+            value  
+                .map(|v| format!("{:.1}{}", v, unit))  
+                .unwrap_or_else(|| "n/a".to_string())  
+        };  
+        sentences.push(format!(  
+            "{}: mean maximum {}, total rainfall {}, mean gust {}.",  
+            city.city,  
+            describe(city.mean_max_temp, "°C"),  
+            describe(city.total_rainfall, " mm"),  
+            describe(city.mean_wind_speed, " km/h")  
+        ));  
+    }  
+
+    sentences.join(" ")  
 }  
 
 fn load_weather_data(file_path: &str) -> Result<DataFrame, Box<dyn Error>> {  
+    // The dataset marks missing readings as "NA", which would otherwise turn numeric columns into text  
     let df = CsvReader::from_path(file_path)?  
         .infer_schema(None)  
         .has_header(true)  
+        .with_null_values(Some(NullValues::AllColumnsSingle("NA".to_string())))  
         .finish()?;  
     Ok(df)  
 }  
 
-fn get_city_month_data(  
-    mut df: DataFrame,  
-    city: &str,  
-    year: i32,  
-    month: u32,  
-) -> DataFrame {  
-    let date_series = df.column("Date").unwrap();  
-    let dates: Vec<NaiveDate> = date_series  
+fn parse_iso_date(s: &str) -> Option<NaiveDate> {  
+    NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d").ok()  
+}  
+
+fn parse_date_range(start_date: &str, end_date: &str) -> Result<(NaiveDate, NaiveDate), String> {  
+    let (start, end) = match (parse_iso_date(start_date), parse_iso_date(end_date)) {  
+        (Some(start), Some(end)) => (start, end),  
+        _ => {  
+            return Err(format!(  
+                "Invalid date range: {} to {}. Expected YYYY-MM-DD.",  
+                start_date, end_date  
+            ))  
+        }  
+    };  
+
+    if start > end {  
+        return Err(format!("Invalid date range: {} is after {}.", start, end));  
+    }  
+
+    Ok((start, end))  
+}  
+
+fn month_bounds(year: i32, month: u32) -> Option<(NaiveDate, NaiveDate)> {  
+    let first = NaiveDate::from_ymd_opt(year, month, 1)?;  
+    let next = if month == 12 {  
+        NaiveDate::from_ymd_opt(year + 1, 1, 1)?  
+    } else {  
+        NaiveDate::from_ymd_opt(year, month + 1, 1)?  
+    };  
+    Some((first, next.pred_opt()?))  
+}  
+
+fn get_dates(df: &DataFrame) -> Vec<Option<NaiveDate>> {  
+    df.column("Date")  
+        .unwrap()  
         .utf8()  
         .unwrap()  
         .into_iter()  
-        .map(|opt_s| {  
-            opt_s.and_then(|s| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok())  
+        .map(|opt_s| opt_s.and_then(parse_iso_date))  
+        .collect()  
+}  
+
+fn get_city_coverage(df: &DataFrame, city: &str) -> Option<(NaiveDate, NaiveDate)> {  
+    let dates = get_dates(df);  
+    let location_series = df.column("Location").unwrap();  
+
+    dates  
+        .iter()  
+        .zip(location_series.utf8().unwrap())  
+        .filter_map(|(date_opt, loc_opt)| match (date_opt, loc_opt) {  
+            (Some(date), Some(loc)) if loc == city => Some(*date),  
+            _ => None,  
         })  
-        .collect();  
+        .fold(None, |acc, date| match acc {  
+            None => Some((date, date)),  
+            Some((first, last)) => Some((first.min(date), last.max(date))),  
+        })  
+}  
 
+fn get_city_range_data(  
+    df: &DataFrame,  
+    city: &str,  
+    start: NaiveDate,  
+    end: NaiveDate,  
+) -> DataFrame {  
+    let dates = get_dates(df);  
     let location_series = df.column("Location").unwrap();  
 
     let mask: BooleanChunked = dates  
@@ -128,7 +1047,7 @@ fn get_city_month_data(
         .zip(location_series.utf8().unwrap())  
         .map(|(date_opt, loc_opt)| {  
             if let (Some(date), Some(loc)) = (date_opt, loc_opt) {  
-                date.year() == year && date.month() == month && loc == city  
+                *date >= start && *date <= end && loc == city  
             } else {  
                 false  
             }  
@@ -138,22 +1057,61 @@ fn get_city_month_data(
     df.filter(&mask).unwrap_or_else(|_| DataFrame::default())  
 }  
 
-fn generate_prompt(df: &DataFrame, city: &str, year: i32, month: u32) -> String {  
-    let csv_data = df.to_csv(String::new()).unwrap_or_else(|_| "".to_string());  
-    format!(  
-        "Below is the weather data for {} during {}/{}:\n\n{}",  
-        city, month, year, csv_data  
-    )  
+fn to_csv(df: &DataFrame) -> String {  
+    let mut buffer = Vec::new();  
+    let written = CsvWriter::new(&mut buffer).has_header(true).finish(&mut df.clone());  
+    match written {  
+        Ok(_) => String::from_utf8(buffer).unwrap_or_default(),  
+        Err(_) => "".to_string(),  
+    }  
+}  
+
+fn generate_prompt(  
+    df: &DataFrame,  
+    city: &str,  
+    period: &str,  
+    anomalies: &[MetricAnomaly],  
+    events: &[WeatherEvent],  
+) -> String {  
+    let csv_data = to_csv(df);  
+    let mut prompt = format!(  
+        "Below is the weather data for {} during {}:\n\n{}",  
+        city, period, csv_data  
+    );  
+
+    if !anomalies.is_empty() {  
+        prompt.push_str("\nComparison with the long-term normals for this month:\n");  
+        for anomaly in anomalies {  
+            prompt.push_str(&format!("- {}: {}\n", anomaly.metric, anomaly.description));  
+        }  
+    }  
+
+    if !events.is_empty() {  
+        prompt.push_str("\nDetected extreme events (report only these, do not infer others):\n");  
+        for event in events {  
+            prompt.push_str(&format!(  
+                "- {} from {} to {} ({} days), peak {:.1}\n",  
+                event.event_type, event.start_date, event.end_date, event.duration_days, event.peak_value  
+            ));  
+        }  
+    }  
+
+    prompt  
 }  
 
-fn load_file_content(path: &str) -> String {  
-    fs::read_to_string(path).unwrap_or_else(|_| "".to_string())  
+// A missing prompt file is an error, so that the LLM is never called without its instructions  
+fn load_file_content(path: &str) -> Result<String, Box<dyn Error>> {  
+    fs::read_to_string(path).map_err(|e| format!("Cannot read prompt file {}: {}", path, e).into())  
 }  
 
 fn get_env_api_key() -> String {  
     env::var("API_KEY").unwrap_or_else(|_| "".to_string())  
 }  
 
+fn get_env_api_url() -> String {  
+    env::var("LLM_API_URL").unwrap_or_else(|_| DEFAULT_LLM_API_URL.to_string())  
+}  
+
 fn call_openai_api(  
     prompt: &str,  
     system_prompt_path: &str,  
@@ -165,9 +1123,14 @@ fn call_openai_api(
     let response = rt.block_on(async {  
         let client = Client::new();  
         let api_key = get_env_api_key();  
-        let system_prompt = load_file_content(system_prompt_path);  
-        let example_input = load_file_content(example_input_path);  
-        let example_output = load_file_content(example_output_path);  
+
+        if api_key.is_empty() {  
+            return Err("API key not provided".into());  
+        }  
+
+        let system_prompt = load_file_content(system_prompt_path)?;  
+        let example_input = load_file_content(example_input_path)?;  
+        let example_output = load_file_content(example_output_path)?;  
 
         let request_body = serde_json::json!({  
             "model": "llama3-8b-8192",  
@@ -180,38 +1143,103 @@ fn call_openai_api(
         });  
 
         let res = client  
-            .post("https://api.openai.com/v1/chat/completions")  
+            .post(get_env_api_url())  
             .bearer_auth(api_key)  
             .json(&request_body)  
             .send()  
             .await?;  
 
+        if !res.status().is_success() {  
+            return Err(format!("Error response from API: {}", res.status()).into());  
+        }  
+
         let res_json: serde_json::Value = res.json().await?;  
-        Ok(res_json["choices"][0]["message"]["content"]  
-            .as_str()  
-            .unwrap_or("")  
-            .to_string())  
+        match res_json["choices"][0]["message"]["content"].as_str() {  
+            Some(content) if !content.trim().is_empty() => Ok::<String, Box<dyn Error>>(content.to_string()),  
+            _ => Err("Missing message content in API response".into()),  
+        }  
     })?;  
 
     Ok(response)  
 }  
 
 fn get_min_temp(df: &DataFrame) -> Vec<Option<f32>> {  
-    df.column("MinTemp")  
-        .unwrap()  
-        .f32()  
-        .unwrap()  
-        .into_iter()  
-        .collect()  
+    get_numeric_values(df, "MinTemp")  
 }  
 
 fn get_max_temp(df: &DataFrame) -> Vec<Option<f32>> {  
-    df.column("MaxTemp")  
-        .unwrap()  
+    get_numeric_values(df, "MaxTemp")  
+}  
+
+// Numeric columns are inferred as i64 or f64 depending on the CSV contents  
+fn get_f32_column(df: &DataFrame, column_name: &str) -> Option<Float32Chunked> {  
+    df.column(column_name)  
+        .ok()?  
+        .cast(&DataType::Float32)  
+        .ok()?  
         .f32()  
-        .unwrap()  
-        .into_iter()  
-        .collect()  
+        .ok()  
+        .cloned()  
+}  
+
+fn get_numeric_values(df: &DataFrame, column_name: &str) -> Vec<Option<f32>> {  
+    get_f32_column(df, column_name)  
+        .map(|series| series.into_iter().collect())  
+        .unwrap_or_else(|| vec![None; df.height()])  
+}  
+
+fn get_metric_series(df: &DataFrame, metric: &str) -> MetricSeries {  
+    let series = |kind: &str, values: Vec<Option<f32>>, labels: Vec<String>| MetricSeries {  
+        metric: metric.to_string(),  
+        error: "".to_string(),  
+        kind: kind.to_string(),  
+        values,  
+        labels,  
+    };  
+    let failed = |error: String| MetricSeries {  
+        metric: metric.to_string(),  
+        error,  
+        kind: "".to_string(),  
+        values: vec![],  
+        labels: vec![],  
+    };  
+
+    let column = match df.column(metric) {  
+        Ok(column) => column,  
+        Err(e) => return failed(format!("Cannot read {}: {}", metric, e)),  
+    };  
+
+    if BOOLEAN_METRICS.contains(&metric) {  
+        return match column.utf8() {  
+            Ok(values) => {  
+                let values = values  
+                    .into_iter()  
+                    .map(|opt_s| match opt_s {  
+                        Some("Yes") => Some(1.0),  
+                        Some("No") => Some(0.0),  
+                        _ => None,  
+                    })  
+                    .collect();  
+                series("boolean", values, vec![])  
+            }  
+            Err(_) => failed(format!("Expected Yes/No values in {}, found {}.", metric, column.dtype())),  
+        };  
+    }  
+
+    match column.dtype() {  
+        DataType::Utf8 => match column.utf8() {  
+            Ok(labels) => {  
+                let labels = labels.into_iter().map(|opt_s| opt_s.unwrap_or("").to_string()).collect();  
+                series("categorical", vec![], labels)  
+            }  
+            Err(e) => failed(format!("Cannot read {}: {}", metric, e)),  
+        },  
+        dtype if dtype.is_numeric() => match get_f32_column(df, metric) {  
+            Some(values) => series("numeric", values.into_iter().collect(), vec![]),  
+            None => failed(format!("Cannot read {} as numbers.", metric)),  
+        },  
+        dtype => failed(format!("Unsupported type {} for {}.", dtype, metric)),  
+    }  
 }  
 
 fn get_wind_direction(df: &DataFrame) -> String {  
@@ -220,25 +1248,528 @@ fn get_wind_direction(df: &DataFrame) -> String {
         .utf8()  
         .unwrap()  
         .mode()  
-        .get(0)  
-        .unwrap_or("")  
-        .to_string()  
+        .ok()  
+        .and_then(|modes| modes.get(0).map(|mode| mode.to_string()))  
+        .unwrap_or_default()  
 }  
 
-fn get_wind_speed(df: &DataFrame) -> Vec<Option<f32>> {  
-    df.column("WindGustSpeed")  
+// One record per calendar day in the range; days without a row keep empty values  
+fn get_daily_weather(df: &DataFrame, start: NaiveDate, end: NaiveDate) -> Vec<DailyWeather> {  
+    let wind_directions: Vec<Option<String>> = df  
+        .column("WindGustDir")  
         .unwrap()  
-        .f32()  
+        .utf8()  
         .unwrap()  
         .into_iter()  
+        .map(|opt_s| opt_s.map(|s| s.to_string()))  
+        .collect();  
+
+    let mut rows: BTreeMap<NaiveDate, DailyWeather> = BTreeMap::new();  
+    let columns = get_dates(df)  
+        .into_iter()  
+        .zip(get_min_temp(df))  
+        .zip(get_max_temp(df))  
+        .zip(wind_directions)  
+        .zip(get_wind_speed(df))  
+        .zip(get_rainfall(df));  
+
+    for (((((date_opt, min_temp), max_temp), wind_direction), wind_speed), rainfall) in columns {  
+        if let Some(date) = date_opt {  
+            rows.insert(  
+                date,  
+                DailyWeather {  
+                    date: date.to_string(),  
+                    min_temp,  
+                    max_temp,  
+                    wind_direction: wind_direction.unwrap_or_default(),  
+                    wind_speed,  
+                    rainfall,  
+                },  
+            );  
+        }  
+    }  
+
+    start  
+        .iter_days()  
+        .take_while(|date| *date <= end)  
+        .map(|date| {  
+            rows.remove(&date).unwrap_or_else(|| DailyWeather {  
+                date: date.to_string(),  
+                min_temp: None,  
+                max_temp: None,  
+                wind_direction: "".to_string(),  
+                wind_speed: None,  
+                rainfall: None,  
+            })  
+        })  
         .collect()  
 }  
 
+fn get_wind_roses(df: &DataFrame) -> Vec<WindRose> {  
+    [  
+        ("WindGustDir", "WindGustSpeed"),  
+        ("WindDir9am", "WindSpeed9am"),  
+        ("WindDir3pm", "WindSpeed3pm"),  
+    ]  
+    .iter()  
+    .map(|(direction_column, speed_column)| get_wind_rose(df, direction_column, speed_column))  
+    .collect()  
+}  
+
+fn get_wind_rose(df: &DataFrame, direction_column: &str, speed_column: &str) -> WindRose {  
+    let directions: Vec<Option<&str>> = df  
+        .column(direction_column)  
+        .ok()  
+        .and_then(|col| col.utf8().ok())  
+        .map(|series| series.into_iter().collect())  
+        .unwrap_or_else(|| vec![None; df.height()]);  
+
+    let speeds = get_numeric_values(df, speed_column);  
+
+    // (count, speed sum, speed count) per compass point  
+    let mut tallies = [(0u32, 0f32, 0u32); 16];  
+    let mut missing = 0u32;  
+
+    for (direction_opt, speed_opt) in directions.iter().zip(&speeds) {  
+        match direction_opt.and_then(|d| COMPASS_POINTS.iter().position(|p| *p == d)) {  
+            Some(idx) => {  
+                tallies[idx].0 += 1;  
+                if let Some(speed) = speed_opt {  
+                    tallies[idx].1 += speed;  
+                    tallies[idx].2 += 1;  
+                }  
+            }  
+            None => missing += 1,  
+        }  
+    }  
+
+    let observations: u32 = tallies.iter().map(|t| t.0).sum();  
+
+    let bins = COMPASS_POINTS  
+        .iter()  
+        .zip(tallies.iter())  
+        .map(|(direction, (count, speed_sum, speed_count))| WindRoseBin {  
+            direction: direction.to_string(),  
+            count: *count,  
+            frequency: if observations > 0 {  
+                *count as f32 / observations as f32  
+            } else {  
+                0.0  
+            },  
+            mean_speed: if *speed_count > 0 {  
+                Some(speed_sum / *speed_count as f32)  
+            } else {  
+                None  
+            },  
+        })  
+        .collect();  
+
+    WindRose {  
+        direction_column: direction_column.to_string(),  
+        speed_column: speed_column.to_string(),  
+        observations,  
+        missing,  
+        bins,  
+    }  
+}  
+
+fn get_wind_speed(df: &DataFrame) -> Vec<Option<f32>> {  
+    get_numeric_values(df, "WindGustSpeed")  
+}  
+
 fn get_rainfall(df: &DataFrame) -> Vec<Option<f32>> {  
-    df.column("Rainfall")  
-        .unwrap()  
-        .f32()  
-        .unwrap()  
+    get_numeric_values(df, "Rainfall")  
+}  
+
+fn column_mean(df: &DataFrame, column_name: &str) -> Option<f32> {  
+    get_f32_column(df, column_name)  
+        .and_then(|series| series.mean())  
+        .map(|v| v as f32)  
+}  
+
+fn column_sum(df: &DataFrame, column_name: &str) -> Option<f32> {  
+    get_f32_column(df, column_name).and_then(|series| series.sum())  
+}  
+
+fn get_mean_diurnal_range(df: &DataFrame) -> Option<f32> {  
+    let ranges: Vec<f32> = get_max_temp(df)  
         .into_iter()  
+        .zip(get_min_temp(df))  
+        .filter_map(|(max, min)| Some(max? - min?))  
+        .collect();  
+
+    if ranges.is_empty() {  
+        None  
+    } else {  
+        Some(ranges.iter().sum::<f32>() / ranges.len() as f32)  
+    }  
+}  
+
+fn get_statistics(df: &DataFrame) -> Vec<MetricSummary> {  
+    ["MinTemp", "MaxTemp", "WindGustSpeed", "Rainfall"]  
+        .iter()  
+        .map(|metric| get_metric_summary(df, metric))  
+        .collect()  
+}  
+
+fn get_metric_summary(df: &DataFrame, column_name: &str) -> MetricSummary {  
+    let series = match get_f32_column(df, column_name) {  
+        Some(series) => series,  
+        None => {  
+            return MetricSummary {  
+                metric: column_name.to_string(),  
+                mean: None,  
+                min: None,  
+                max: None,  
+                std_dev: None,  
+                median: None,  
+                p10: None,  
+                p90: None,  
+                null_count: df.height() as u32,  
+                observation_count: 0,  
+            }  
+        }  
+    };  
+
+    let quantile = |q: f64| -> Option<f32> {  
+        series  
+            .quantile(q, QuantileInterpolOptions::Linear)  
+            .ok()  
+            .flatten()  
+    };  
+
+    MetricSummary {  
+        metric: column_name.to_string(),  
+        mean: series.mean().map(|v| v as f32),  
+        min: series.min(),  
+        max: series.max(),  
+        std_dev: series.std(1),  
+        median: series.median(),  
+        p10: quantile(0.1),  
+        p90: quantile(0.9),  
+        null_count: series.null_count() as u32,  
+        observation_count: (series.len() - series.null_count()) as u32,  
+    }  
+}  
+
+fn get_monthly_anomalies(df: &DataFrame, city: &str, year: i32, month: u32) -> Vec<MetricAnomaly> {  
+    // (column, unit, aggregate as monthly total instead of mean)  
+    let metrics = [  
+        ("MinTemp", "°C", false),  
+        ("MaxTemp", "°C", false),  
+        ("WindGustSpeed", " km/h", false),  
+        ("Rainfall", " mm", true),  
+    ];  
+
+    let dates = get_dates(df);  
+    let location_series = df.column("Location").unwrap();  
+    let locations: Vec<Option<&str>> = location_series.utf8().unwrap().into_iter().collect();  
+
+    metrics  
+        .iter()  
+        .map(|(metric, unit, total)| {  
+            let values = get_numeric_values(df, metric);  
+
+            // Aggregate the calendar month for each year on record  
+            let mut by_year: BTreeMap<i32, (f32, u32)> = BTreeMap::new();  
+            for ((date_opt, loc_opt), value_opt) in dates.iter().zip(&locations).zip(&values) {  
+                if let (Some(date), Some(loc), Some(value)) = (date_opt, loc_opt, value_opt) {  
+                    if *loc == city && date.month() == month {  
+                        let entry = by_year.entry(date.year()).or_insert((0.0, 0));  
+                        entry.0 += value;  
+                        entry.1 += 1;  
+                    }  
+                }  
+            }  
+
+            let yearly: BTreeMap<i32, f32> = by_year  
+                .into_iter()  
+                .map(|(y, (sum, count))| (y, if *total { sum } else { sum / count as f32 }))  
+                .collect();  
+
+            get_metric_anomaly(metric, unit, year, &yearly)  
+        })  
+        .collect()  
+}  
+
+fn get_metric_anomaly(metric: &str, unit: &str, year: i32, yearly: &BTreeMap<i32, f32>) -> MetricAnomaly {  
+    let value = yearly.get(&year).copied();  
+    let baseline: Vec<(i32, f32)> = yearly  
+        .iter()  
+        .filter(|(y, _)| **y != year)  
+        .map(|(y, v)| (*y, *v))  
+        .collect();  
+
+    let baseline_start_year = baseline.first().map(|(y, _)| *y).unwrap_or(0);  
+    let baseline_end_year = baseline.last().map(|(y, _)| *y).unwrap_or(0);  
+
+    let normal = if baseline.is_empty() {  
+        None  
+    } else {  
+        Some(baseline.iter().map(|(_, v)| v).sum::<f32>() / baseline.len() as f32)  
+    };  
+
+    let (anomaly, percentile) = match (value, normal) {  
+        (Some(v), Some(n)) => {  
+            let below = baseline.iter().filter(|(_, b)| *b < v).count() as f32;  
+            let equal = baseline.iter().filter(|(_, b)| *b == v).count() as f32;  
+            let percentile = (below + 0.5 * equal) / baseline.len() as f32 * 100.0;  
+            (Some(v - n), Some(percentile))  
+        }  
+        _ => (None, None),  
+    };  
+
+    let description = match (value, normal, anomaly, percentile) {  
+        (Some(v), Some(n), Some(a), Some(p)) => format!(  
+            "{:.1}{}, {:+.1}{} {} the {}–{} mean of {:.1}{}, at the {} percentile",  
+            v,  
+            unit,  
+            a,  
+            unit,  
+            if a >= 0.0 { "above" } else { "below" },  
+            baseline_start_year,  
+            baseline_end_year,  
+            n,  
+            unit,  
+            ordinal(p.round() as u32)  
+        ),  
+        (None, _, _, _) => "no data for the requested month".to_string(),  
+        _ => "no other years on record to compare against".to_string(),  
+    };  
+
+    MetricAnomaly {  
+        metric: metric.to_string(),  
+        value,  
+        normal,  
+        anomaly,  
+        percentile,  
+        baseline_years: baseline.len() as u32,  
+        baseline_start_year,  
+        baseline_end_year,  
+        description,  
+    }  
+}  
+
+fn ordinal(n: u32) -> String {  
+    let suffix = match (n % 10, n % 100) {  
+        (_, 11..=13) => "th",  
+        (1, _) => "st",  
+        (2, _) => "nd",  
+        (3, _) => "rd",  
+        _ => "th",  
+    };  
+    format!("{}{}", n, suffix)  
+}  
+
+fn detect_events(df: &DataFrame, thresholds: &EventThresholds) -> Vec<WeatherEvent> {  
+    let dates = get_dates(df);  
+    let day_series = |values: Vec<Option<f32>>| -> Vec<(NaiveDate, Option<f32>)> {  
+        let mut days: Vec<(NaiveDate, Option<f32>)> = dates  
+            .iter()  
+            .zip(values)  
+            .filter_map(|(date_opt, value)| date_opt.map(|date| (date, value)))  
+            .collect();  
+        days.sort_by_key(|(date, _)| *date);  
+        days  
+    };  
+
+    let max_temp = day_series(get_max_temp(df));  
+    let min_temp = day_series(get_min_temp(df));  
+    let wind_speed = day_series(get_wind_speed(df));  
+    let rainfall = day_series(get_rainfall(df));  
+
+    let mut events = vec![];  
+    events.extend(detect_runs(  
+        &max_temp,  
+        "heatwave",  
+        thresholds.heatwave_min_days,  
+        |v| v > thresholds.heatwave_max_temp,  
+        f32::max,  
+    ));  
+    events.extend(detect_days(&rainfall, "heavy_rain", |v| v >= thresholds.heavy_rain_mm));  
+    events.extend(detect_days(&min_temp, "frost", |v| v <= thresholds.frost_min_temp));  
+    events.extend(detect_days(&wind_speed, "damaging_gust", |v| {  
+        v >= thresholds.damaging_gust_speed  
+    }));  
+    events.extend(detect_runs(  
+        &rainfall,  
+        "dry_spell",  
+        thresholds.dry_spell_min_days,  
+        |v| v <= thresholds.dry_day_max_rain_mm,  
+        f32::min,  
+    ));  
+
+    events.sort_by(|a, b| a.start_date.cmp(&b.start_date));  
+    events  
+}  
+
+fn detect_days(  
+    days: &[(NaiveDate, Option<f32>)],  
+    event_type: &str,  
+    predicate: impl Fn(f32) -> bool,  
+) -> Vec<WeatherEvent> {  
+    days.iter()  
+        .filter_map(|(date, value)| match value {  
+            Some(v) if predicate(*v) => Some(WeatherEvent {  
+                event_type: event_type.to_string(),  
+                start_date: date.to_string(),  
+                end_date: date.to_string(),  
+                duration_days: 1,  
+                peak_value: *v,  
+            }),  
+            _ => None,  
+        })  
         .collect()  
+}  
+
+// A run is broken by a missing value, a gap in dates, or a day failing the predicate  
+fn detect_runs(  
+    days: &[(NaiveDate, Option<f32>)],  
+    event_type: &str,  
+    min_days: u32,  
+    predicate: impl Fn(f32) -> bool,  
+    peak: fn(f32, f32) -> f32,  
+) -> Vec<WeatherEvent> {  
+    let mut events = vec![];  
+    let mut run: Option<(NaiveDate, NaiveDate, u32, f32)> = None;  
+
+    let mut close_run = |run: Option<(NaiveDate, NaiveDate, u32, f32)>| {  
+        if let Some((start, end, length, peak_value)) = run {  
+            if length >= min_days.max(1) {  
+                events.push(WeatherEvent {  
+                    event_type: event_type.to_string(),  
+                    start_date: start.to_string(),  
+                    end_date: end.to_string(),  
+                    duration_days: length,  
+                    peak_value,  
+                });  
+            }  
+        }  
+    };  
+
+    for (date, value) in days {  
+        run = match (run, value) {  
+            (Some((start, end, length, peak_value)), Some(v))  
+                if predicate(*v) && end.succ_opt() == Some(*date) =>  
+            {  
+                Some((start, *date, length + 1, peak(peak_value, *v)))  
+            }  
+            (previous, Some(v)) if predicate(*v) => {  
+                close_run(previous);  
+                Some((*date, *date, 1, *v))  
+            }  
+            (previous, _) => {  
+                close_run(previous);  
+                None  
+            }  
+        };  
+    }  
+    close_run(run);  
+
+    events  
+}  
+
+#[cfg(test)]  
+mod tests {  
+    use super::*;  
+
+    fn days(start: &str, values: &[Option<f32>]) -> Vec<(NaiveDate, Option<f32>)> {  
+        let start = parse_iso_date(start).unwrap();  
+        values  
+            .iter()  
+            .enumerate()  
+            .map(|(i, value)| (start + chrono::Duration::days(i as i64), *value))  
+            .collect()  
+    }  
+
+    fn spans(events: &[WeatherEvent]) -> Vec<(&str, &str, u32, f32)> {  
+        events  
+            .iter()  
+            .map(|e| (e.start_date.as_str(), e.end_date.as_str(), e.duration_days, e.peak_value))  
+            .collect()  
+    }  
+
+    #[test]  
+    fn runs_shorter_than_the_minimum_are_dropped() {  
+        let temps = days("2020-01-01", &[Some(36.0), Some(38.0), Some(30.0), Some(36.0), Some(37.0), Some(39.0)]);  
+
+        let events = detect_runs(&temps, "heatwave", 3, |v| v > 35.0, f32::max);  
+        assert_eq!(spans(&events), vec![("2020-01-04", "2020-01-06", 3, 39.0)]);  
+    }  
+
+    #[test]  
+    fn missing_values_and_date_gaps_break_a_run() {  
+        let mut temps = days("2020-01-01", &[Some(36.0), Some(37.0), None, Some(36.0), Some(36.0)]);  
+        // 2020-01-07 follows 2020-01-05 with no row for the 6th  
+        temps.extend(days("2020-01-07", &[Some(40.0)]));  
+
+        let events = detect_runs(&temps, "heatwave", 1, |v| v > 35.0, f32::max);  
+        assert_eq!(  
+            spans(&events),  
+            vec![  
+                ("2020-01-01", "2020-01-02", 2, 37.0),  
+                ("2020-01-04", "2020-01-05", 2, 36.0),  
+                ("2020-01-07", "2020-01-07", 1, 40.0),  
+            ]  
+        );  
+    }  
+
+    #[test]  
+    fn dry_spell_counts_days_at_the_threshold_and_peaks_at_the_driest_day() {  
+        let thresholds = EventThresholds {  
+            dry_spell_min_days: 3,  
+            ..default_event_thresholds()  
+        };  
+        let rainfall = days("2020-01-01", &[Some(1.0), Some(0.4), Some(0.0), Some(0.8), Some(5.0)]);  
+        let df = DataFrame::new(vec![  
+            Series::new("Date", rainfall.iter().map(|(d, _)| d.to_string()).collect::<Vec<_>>()),  
+            Series::new("Rainfall", rainfall.iter().map(|(_, v)| *v).collect::<Vec<_>>()),  
+        ])  
+        .unwrap();  
+
+        let events: Vec<WeatherEvent> = detect_events(&df, &thresholds)  
+            .into_iter()  
+            .filter(|e| e.event_type == "dry_spell")  
+            .collect();  
+        assert_eq!(spans(&events), vec![("2020-01-01", "2020-01-04", 4, 0.0)]);  
+    }  
+
+    #[test]  
+    fn metrics_are_read_from_a_csv_with_missing_readings() {  
+        let path = std::env::temp_dir().join(format!("pipeline1-metrics-{}.csv", std::process::id()));  
+        fs::write(  
+            &path,  
+            "Date,Location,MinTemp,WindGustDir,RainToday\n\  
+             2020-01-01,Sydney,NA,NE,No\n\  
+             2020-01-02,Sydney,18.5,NA,Yes\n",  
+        )  
+        .unwrap();  
+        let df = load_weather_data(path.to_str().unwrap()).unwrap();  
+
+        let min_temp = get_metric_series(&df, "MinTemp");  
+        assert_eq!(min_temp.kind, "numeric");  
+        assert_eq!(min_temp.values, vec![None, Some(18.5)]);  
+
+        let direction = get_metric_series(&df, "WindGustDir");  
+        assert_eq!(direction.kind, "categorical");  
+        assert_eq!(direction.labels, vec!["NE".to_string(), "".to_string()]);  
+
+        let rain = get_metric_series(&df, "RainToday");  
+        assert_eq!(rain.values, vec![Some(0.0), Some(1.0)]);  
+
+        let missing = get_metric_series(&df, "RainTomorrow");  
+        assert!(missing.error.contains("RainTomorrow"), "{}", missing.error);  
+        assert!(missing.values.is_empty());  
+    }  
+
+    #[test]  
+    fn single_day_events_skip_missing_values() {  
+        let rainfall = days("2020-01-01", &[Some(30.0), None, Some(10.0), Some(25.0)]);  
+
+        let events = detect_days(&rainfall, "heavy_rain", |v| v >= 25.0);  
+        assert_eq!(  
+            spans(&events),  
+            vec![("2020-01-01", "2020-01-01", 1, 30.0), ("2020-01-04", "2020-01-04", 1, 25.0)]  
+        );  
+    }  
 }
\ No newline at end of file
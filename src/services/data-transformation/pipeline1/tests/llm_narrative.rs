@@ -0,0 +1,153 @@
+// tests/llm_narrative.rs  
+
+// Runs the LLM narrative and its template fallback against the mock server  
+
+#[path = "../src/main.rs"]  
+#[allow(dead_code)]  
+mod pipeline1;  
+
+use std::env;  
+use std::fs;  
+use std::process;  
+use std::sync::{Mutex, MutexGuard};  
+
+use chrono::{Datelike, Duration, NaiveDate};  
+use mock_server::fixtures;  
+use mock_server::{MockServer, Route};  
+use serde_json::Value;  
+
+use pipeline1::{generate_comparative_weather_report, generate_weather_report};  
+
+static ENV_LOCK: Mutex<()> = Mutex::new(());  
+
+fn stub(scenario: &str) -> (MutexGuard<'static, ()>, MockServer) {  
+    let guard = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());  
+    let routes: Vec<Route> = fixtures::scenario(scenario).unwrap();  
+    let server = MockServer::start(routes).unwrap();  
+
+    write_dataset();  
+    env::set_var("API_KEY", "test-key");  
+    env::set_var("LLM_API_URL", format!("{}{}", server.url(), fixtures::CHAT_COMPLETIONS_PATH));  
+
+    (guard, server)  
+}  
+
+// Two years of daily readings for one location, plus the prompt files next to them  
+fn write_dataset() {  
+    let dir = env::temp_dir().join(format!("pipeline1-tests-{}", process::id()));  
+    fs::create_dir_all(&dir).unwrap();  
+
+    let mut csv = "Date,Location,MinTemp,MaxTemp,Rainfall,WindGustDir,WindGustSpeed\n".to_string();  
+    let start = NaiveDate::from_ymd_opt(2015, 1, 1).unwrap();  
+    for i in 0..730 {  
+        let date = start + Duration::days(i as i64);  
+        let season = (date.ordinal() as f32 / 365.0 * 2.0 * std::f32::consts::PI).cos();  
+        csv.push_str(&format!(  
+            "{},Sydney,{:.1},{:.1},{:.1},{},{:.1}\n",  
+            date.format("%Y-%m-%d"),  
+            14.0 + 5.0 * season,  
+            22.0 + 5.0 * season,  
+            (i % 5) as f32 * 1.5,  
+            ["NE", "S", "W"][i % 3],  
+            35.0 + (i % 7) as f32  
+        ));  
+    }  
+
+    fs::write(dir.join("weatherAUS.csv"), csv).unwrap();  
+    for file in ["system_prompt.txt", "example_input1.txt", "example_output1.txt"].iter() {  
+        fs::write(dir.join(file), "Example").unwrap();  
+    }  
+    env::set_current_dir(&dir).unwrap();  
+}  
+
+fn report(mode: &str) -> Value {  
+    let report = generate_weather_report("Sydney".to_string(), 2016, 3, mode.to_string());  
+    serde_json::to_value(&report).unwrap()  
+}  
+
+#[test]  
+fn llm_reply_becomes_the_report() {  
+    let (_guard, server) = stub("ok");  
+
+    let report = report("llm");  
+    let reply: Value = serde_json::from_str(fixtures::CHAT_COMPLETION).unwrap();  
+    assert_eq!(report["narrative_source"], "llm");  
+    assert_eq!(report["llm_error"], "");  
+    assert_eq!(report["report"], reply["choices"][0]["message"]["content"]);  
+
+    let requests = server.requests();  
+    assert_eq!(requests.len(), 1);  
+    assert!(requests[0].starts_with("POST /v1/chat/completions"), "{}", requests[0]);  
+}  
+
+#[test]  
+fn failed_llm_call_falls_back_to_the_template() {  
+    let cases = [  
+        ("llm_rate_limited", "429"),  
+        ("llm_missing_content", "Missing message content"),  
+        ("malformed_json", "decoding"),  
+    ];  
+
+    for (scenario, error) in cases.iter() {  
+        let (_guard, server) = stub(scenario);  
+
+        let report = report("llm");  
+        assert_eq!(report["narrative_source"], "template_fallback", "{}", scenario);  
+        let llm_error = report["llm_error"].as_str().unwrap();  
+        assert!(llm_error.contains(error), "{}: {}", scenario, llm_error);  
+        assert!(report["report"].as_str().unwrap().starts_with("Weather report for Sydney, 3/2016."));  
+        assert_eq!(server.requests().len(), 1, "{}", scenario);  
+    }  
+}  
+
+#[test]  
+fn template_mode_and_missing_key_send_no_request() {  
+    let (_guard, server) = stub("ok");  
+
+    let report_without_llm = report("template");  
+    assert_eq!(report_without_llm["narrative_source"], "template");  
+    assert_eq!(report_without_llm["llm_error"], "");  
+
+    env::remove_var("API_KEY");  
+    let report_without_key = report("llm");  
+    assert_eq!(report_without_key["narrative_source"], "template_fallback");  
+    assert_eq!(report_without_key["llm_error"], "API key not provided");  
+    assert_eq!(report_without_key["report"], report_without_llm["report"]);  
+
+    assert!(server.requests().is_empty());  
+}  
+
+#[test]  
+fn comparison_needs_its_own_prompt_files() {  
+    let (_guard, server) = stub("ok");  
+    let files = [  
+        "system_prompt_comparison.txt",  
+        "example_input_comparison.txt",  
+        "example_output_comparison.txt",  
+    ];  
+    let compare = || {  
+        let report = generate_comparative_weather_report(  
+            vec!["Sydney".to_string()],  
+            "2016-03-01".to_string(),  
+            "2016-03-31".to_string(),  
+            "llm".to_string(),  
+        );  
+        serde_json::to_value(&report).unwrap()  
+    };  
+
+    for file in files.iter() {  
+        let _ = fs::remove_file(file);  
+    }  
+    let report = compare();  
+    assert_eq!(report["narrative_source"], "template_fallback");  
+    let llm_error = report["llm_error"].as_str().unwrap();  
+    assert!(llm_error.contains("system_prompt_comparison.txt"), "{}", llm_error);  
+    assert!(server.requests().is_empty());  
+
+    for file in files.iter() {  
+        fs::write(file, "Example").unwrap();  
+    }  
+    let report = compare();  
+    assert_eq!(report["narrative_source"], "llm");  
+    assert_eq!(server.requests().len(), 1);  
+}
\ No newline at end of file
@@ -0,0 +1,258 @@
+// src/decompose.rs  
+
+// Trend and seasonality analysis on the per-period aggregates built by get_city_period_data  
+
+const DECOMPOSITION_PASSES: usize = 2;  
+
+pub struct Decomposition {  
+    pub trend: Vec<f32>,  
+    pub seasonal: Vec<f32>,  
+    pub residual: Vec<f32>,  
+}  
+
+pub struct TrendTest {  
+    // Sen's slope in units per period, with the matching intercept at period 0  
+    pub slope: f32,  
+    pub intercept: f32,  
+    // Seasonal Kendall statistic and its two-sided p-value  
+    pub z: f32,  
+    pub p_value: f32,  
+}  
+
+// STL-style decomposition: alternates a moving-average trend with cycle-subseries  
+// means for the seasonal part, so each pass refines one against the other  
+pub fn decompose(series: &[f32], season_length: usize) -> Decomposition {  
+    let n = series.len();  
+    let mut seasonal = vec![0.0; n];  
+    let mut trend = vec![0.0; n];  
+
+    for _ in 0..DECOMPOSITION_PASSES {  
+        let deseasonalised: Vec<f32> = series.iter().zip(&seasonal).map(|(v, s)| v - s).collect();  
+        trend = moving_average(&deseasonalised, season_length);  
+
+        let detrended: Vec<f32> = series.iter().zip(&trend).map(|(v, t)| v - t).collect();  
+        seasonal = seasonal_means(&detrended, season_length);  
+    }  
+
+    let residual = (0..n).map(|i| series[i] - trend[i] - seasonal[i]).collect();  
+
+    Decomposition {  
+        trend,  
+        seasonal,  
+        residual,  
+    }  
+}  
+
+// Centred moving average over one season (2 x m for even m); near the ends,  
+// where the window does not fit, the nearest full-window value is carried out  
+fn moving_average(series: &[f32], window: usize) -> Vec<f32> {  
+    let n = series.len();  
+    let half = window / 2;  
+    if n == 0 || window < 2 || n <= 2 * half {  
+        let mean = series.iter().sum::<f32>() / n.max(1) as f32;  
+        return vec![mean; n];  
+    }  
+
+    let mut averaged = vec![0.0; n];  
+    for (i, value) in averaged.iter_mut().enumerate().take(n - half).skip(half) {  
+        *value = if window.is_multiple_of(2) {  
+            let inner: f32 = series[i + 1 - half..i + half].iter().sum();  
+            (inner + 0.5 * (series[i - half] + series[i + half])) / window as f32  
+        } else {  
+            series[i - half..=i + half].iter().sum::<f32>() / window as f32  
+        };  
+    }  
+
+    let (first, last) = (averaged[half], averaged[n - half - 1]);  
+    for value in averaged.iter_mut().take(half) {  
+        *value = first;  
+    }  
+    for value in averaged.iter_mut().skip(n - half) {  
+        *value = last;  
+    }  
+
+    averaged  
+}  
+
+// Mean of each position in the season, centred so that a full season sums to zero  
+fn seasonal_means(detrended: &[f32], season_length: usize) -> Vec<f32> {  
+    let mut sums = vec![(0.0, 0); season_length];  
+    for (i, value) in detrended.iter().enumerate() {  
+        sums[i % season_length].0 += value;  
+        sums[i % season_length].1 += 1;  
+    }  
+
+    let means: Vec<f32> = sums  
+        .iter()  
+        .map(|(sum, count)| if *count > 0 { sum / *count as f32 } else { 0.0 })  
+        .collect();  
+    let offset = means.iter().sum::<f32>() / season_length as f32;  
+
+    (0..detrended.len()).map(|i| means[i % season_length] - offset).collect()  
+}  
+
+// Seasonal Kendall test (Hirsch et al., 1982) with the matching Sen's slope: each value is  
+// only compared with the same position of the season in other years, so the annual cycle  
+// cannot pass for a trend, and values a year apart carry far less autocorrelation than  
+// neighbouring months. A season length of 1 gives the plain Mann-Kendall test. Non-finite  
+// values, e.g. months left empty by the drop imputation, are skipped  
+pub fn trend_test(series: &[f32], season_length: usize) -> TrendTest {  
+    let season_length = season_length.max(1);  
+
+    let mut slopes = vec![];  
+    let mut s: i64 = 0;  
+    let mut variance = 0.0;  
+    for season in 0..season_length {  
+        let values: Vec<(usize, f32)> = series  
+            .iter()  
+            .copied()  
+            .enumerate()  
+            .skip(season)  
+            .step_by(season_length)  
+            .filter(|(_, v)| v.is_finite())  
+            .collect();  
+
+        for (k, (i, earlier)) in values.iter().enumerate() {  
+            for (j, later) in &values[k + 1..] {  
+                slopes.push((later - earlier) / (j - i) as f32);  
+                s += match later.total_cmp(earlier) {  
+                    std::cmp::Ordering::Greater => 1,  
+                    std::cmp::Ordering::Less => -1,  
+                    std::cmp::Ordering::Equal => 0,  
+                };  
+            }  
+        }  
+
+        variance += kendall_variance(values.into_iter().map(|(_, v)| v).collect());  
+    }  
+
+    let slope = median(&mut slopes);  
+    let mut offsets: Vec<f32> = series  
+        .iter()  
+        .enumerate()  
+        .filter(|(_, v)| v.is_finite())  
+        .map(|(i, v)| v - slope * i as f32)  
+        .collect();  
+    let intercept = median(&mut offsets);  
+
+    let s = s as f64;  
+    let z = if variance <= 0.0 {  
+        0.0  
+    } else if s > 0.0 {  
+        (s - 1.0) / variance.sqrt()  
+    } else if s < 0.0 {  
+        (s + 1.0) / variance.sqrt()  
+    } else {  
+        0.0  
+    };  
+
+    TrendTest {  
+        slope,  
+        intercept,  
+        z: z as f32,  
+        p_value: (2.0 * (1.0 - normal_cdf(z.abs()))) as f32,  
+    }  
+}  
+
+// Variance of the Mann-Kendall S statistic with no trend, corrected for tied values  
+fn kendall_variance(mut values: Vec<f32>) -> f64 {  
+    values.sort_by(|a, b| a.total_cmp(b));  
+
+    let mut tie_correction = 0.0;  
+    let mut i = 0;  
+    while i < values.len() {  
+        let mut j = i;  
+        while j < values.len() && values[j] == values[i] {  
+            j += 1;  
+        }  
+        let t = (j - i) as f64;  
+        tie_correction += t * (t - 1.0) * (2.0 * t + 5.0);  
+        i = j;  
+    }  
+
+    let n = values.len() as f64;  
+    (n * (n - 1.0) * (2.0 * n + 5.0) - tie_correction) / 18.0  
+}  
+
+fn median(values: &mut [f32]) -> f32 {  
+    if values.is_empty() {  
+        return 0.0;  
+    }  
+    values.sort_by(|a, b| a.total_cmp(b));  
+    let mid = values.len() / 2;  
+    if values.len().is_multiple_of(2) {  
+        (values[mid - 1] + values[mid]) / 2.0  
+    } else {  
+        values[mid]  
+    }  
+}  
+
+// Abramowitz and Stegun 7.1.26  
+fn normal_cdf(x: f64) -> f64 {  
+    let t = 1.0 / (1.0 + 0.327_591_1 * x.abs() / std::f64::consts::SQRT_2);  
+    let poly = t * (0.254_829_592 + t * (-0.284_496_736 + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));  
+    let erf = 1.0 - poly * (-(x * x) / 2.0).exp();  
+    if x >= 0.0 {  
+        0.5 * (1.0 + erf)  
+    } else {  
+        0.5 * (1.0 - erf)  
+    }  
+}  
+
+#[cfg(test)]  
+mod tests {  
+    use super::*;  
+
+    const PATTERN: [f32; 4] = [5.0, -2.0, 3.0, -6.0];  
+
+    fn seasonal_series(years: usize, slope: f32) -> Vec<f32> {  
+        (0..years * PATTERN.len())  
+            .map(|t| 20.0 + slope * t as f32 + PATTERN[t % PATTERN.len()])  
+            .collect()  
+    }  
+
+    #[test]  
+    fn sen_slope_recovers_a_linear_series() {  
+        let series: Vec<f32> = (0..20).map(|t| 2.0 + 0.5 * t as f32).collect();  
+        let test = trend_test(&series, 1);  
+        assert!((test.slope - 0.5).abs() < 1e-6);  
+        assert!((test.intercept - 2.0).abs() < 1e-5);  
+        assert!(test.z > 0.0);  
+        assert!(test.p_value < 0.001);  
+    }  
+
+    #[test]  
+    fn seasonal_kendall_ignores_the_annual_cycle() {  
+        let flat = trend_test(&seasonal_series(6, 0.0), PATTERN.len());  
+        assert_eq!(flat.slope, 0.0);  
+        assert_eq!(flat.p_value, 1.0);  
+
+        let rising = trend_test(&seasonal_series(6, 0.25), PATTERN.len());  
+        assert!((rising.slope - 0.25).abs() < 1e-5);  
+        assert!(rising.p_value < 0.001);  
+    }  
+
+    #[test]  
+    fn trend_test_skips_non_finite_values() {  
+        let mut series = seasonal_series(6, -0.25);  
+        series[5] = f32::NAN;  
+        series[10] = f32::INFINITY;  
+        let test = trend_test(&series, PATTERN.len());  
+        assert!((test.slope + 0.25).abs() < 1e-5);  
+        assert!(test.z < 0.0);  
+    }  
+
+    #[test]  
+    fn decompose_separates_trend_and_season() {  
+        let series = seasonal_series(6, 0.25);  
+        let decomposition = decompose(&series, PATTERN.len());  
+
+        // Away from the ends, where the moving average is carried out flat, the trend is exact  
+        for t in 2..series.len() - 2 {  
+            assert!((decomposition.trend[t] - (20.0 + 0.25 * t as f32)).abs() < 1e-3);  
+        }  
+        for (t, seasonal) in decomposition.seasonal.iter().enumerate() {  
+            assert!((seasonal - PATTERN[t % PATTERN.len()]).abs() < 0.25);  
+        }  
+    }  
+}
\ No newline at end of file
@@ -0,0 +1,354 @@
+// src/forecast.rs  
+
+// In-process forecasting on the per-period aggregates built by get_city_period_data  
+
+const SMOOTHING_GRID: [f32; 9] = [0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9];  
+
+#[derive(Debug, Clone, Copy, PartialEq)]  
+pub enum Method {  
+    SeasonalNaive,  
+    ExponentialSmoothing,  
+    HoltWinters,  
+}  
+
+impl Method {  
+    pub fn parse(method: &str) -> Option<Method> {  
+        match method.trim().to_lowercase().as_str() {  
+            "seasonal_naive" => Some(Method::SeasonalNaive),  
+            "exponential_smoothing" | "ses" => Some(Method::ExponentialSmoothing),  
+            "holt_winters" => Some(Method::HoltWinters),  
+            _ => None,  
+        }  
+    }  
+}  
+
+pub struct Forecast {  
+    pub values: Vec<f32>,  
+    // Standard error of each step, used for prediction intervals  
+    pub std_errors: Vec<f32>,  
+}  
+
+// `seasons` gives the season (0..season_length) of every observation followed by every forecast  
+// step. Taking it from the calendar keeps weeks and days aligned across years with an ISO week 53  
+// or a leap day, where the position in the series would drift by one  
+pub fn forecast_seasons(  
+    series: &[f32],  
+    seasons: &[usize],  
+    horizon: usize,  
+    season_length: usize,  
+    method: Method,  
+) -> Forecast {  
+    if series.is_empty() {  
+        return Forecast {  
+            values: vec![0.0; horizon],  
+            std_errors: vec![0.0; horizon],  
+        };  
+    }  
+
+    match method {  
+        Method::SeasonalNaive => seasonal_naive(series, seasons, horizon, season_length),  
+        Method::ExponentialSmoothing => exponential_smoothing(series, horizon),  
+        // Holt-Winters needs two full seasons to initialise its trend and seasonal terms  
+        Method::HoltWinters if series.len() >= 2 * season_length => {  
+            holt_winters(series, seasons, horizon, season_length)  
+        }  
+        Method::HoltWinters => exponential_smoothing(series, horizon),  
+    }  
+}  
+
+// Two-sided standard normal quantile for a confidence level such as 0.8 or 0.95  
+pub fn z_score(confidence_level: f32) -> f32 {  
+    normal_quantile(0.5 + confidence_level as f64 / 2.0) as f32  
+}  
+
+// Acklam's rational approximation of the inverse standard normal CDF. The coefficients are  
+// kept as published, though f64 cannot hold their last digit exactly  
+#[allow(clippy::excessive_precision)]  
+fn normal_quantile(p: f64) -> f64 {  
+    const A: [f64; 6] = [  
+        -3.969683028665376e+01,  
+        2.209460984245205e+02,  
+        -2.759285104469687e+02,  
+        1.383577518672690e+02,  
+        -3.066479806614716e+01,  
+        2.506628277459239e+00,  
+    ];  
+    const B: [f64; 5] = [  
+        -5.447609879822406e+01,  
+        1.615858368580409e+02,  
+        -1.556989798598866e+02,  
+        6.680131188771972e+01,  
+        -1.328068155288572e+01,  
+    ];  
+    const C: [f64; 6] = [  
+        -7.784894002430293e-03,  
+        -3.223964580411365e-01,  
+        -2.400758277161838e+00,  
+        -2.549732539343734e+00,  
+        4.374664141464968e+00,  
+        2.938163982698783e+00,  
+    ];  
+    const D: [f64; 4] = [  
+        7.784695709041462e-03,  
+        3.224671290700398e-01,  
+        2.445134137142996e+00,  
+        3.754408661907416e+00,  
+    ];  
+    const P_LOW: f64 = 0.02425;  
+
+    let tail = |q: f64| {  
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])  
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)  
+    };  
+
+    if p <= 0.0 || p >= 1.0 {  
+        f64::NAN  
+    } else if p < P_LOW {  
+        tail((-2.0 * p.ln()).sqrt())  
+    } else if p > 1.0 - P_LOW {  
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())  
+    } else {  
+        let q = p - 0.5;  
+        let r = q * q;  
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q  
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)  
+    }  
+}  
+
+fn residual_std(errors: &[f32]) -> f32 {  
+    if errors.is_empty() {  
+        return 0.0;  
+    }  
+    (errors.iter().map(|e| e * e).sum::<f32>() / errors.len() as f32).sqrt()  
+}  
+
+fn seasonal_naive(series: &[f32], seasons: &[usize], horizon: usize, season_length: usize) -> Forecast {  
+    let n = series.len();  
+
+    if n < season_length {  
+        // Too short for a season, fall back to a random walk  
+        let errors: Vec<f32> = series.windows(2).map(|w| w[1] - w[0]).collect();  
+        let sigma = residual_std(&errors);  
+        return Forecast {  
+            values: vec![series[n - 1]; horizon],  
+            std_errors: (1..=horizon).map(|h| sigma * (h as f32).sqrt()).collect(),  
+        };  
+    }  
+
+    // Each step repeats the latest observation of its season  
+    let mut latest: Vec<Option<f32>> = vec![None; season_length];  
+    let mut errors = vec![];  
+    for (value, season) in series.iter().zip(seasons) {  
+        if let Some(previous) = latest[*season] {  
+            errors.push(value - previous);  
+        }  
+        latest[*season] = Some(*value);  
+    }  
+    let sigma = residual_std(&errors);  
+
+    Forecast {  
+        values: seasons[n..n + horizon]  
+            .iter()  
+            .map(|season| latest[*season].unwrap_or(series[n - 1]))  
+            .collect(),  
+        std_errors: (0..horizon)  
+            .map(|h| sigma * ((h / season_length + 1) as f32).sqrt())  
+            .collect(),  
+    }  
+}  
+
+fn exponential_smoothing(series: &[f32], horizon: usize) -> Forecast {  
+    let (level, sse, alpha) = SMOOTHING_GRID  
+        .iter()  
+        .map(|alpha| fit_exponential_smoothing(series, *alpha))  
+        .fold((series[0], f32::INFINITY, 0.0), |best, fit| if fit.1 < best.1 { fit } else { best });  
+
+    let sigma = if series.len() > 1 {  
+        (sse / (series.len() - 1) as f32).sqrt()  
+    } else {  
+        0.0  
+    };  
+
+    Forecast {  
+        values: vec![level; horizon],  
+        std_errors: (0..horizon)  
+            .map(|h| sigma * (1.0 + h as f32 * alpha * alpha).sqrt())  
+            .collect(),  
+    }  
+}  
+
+// Returns the final level, the one-step-ahead sum of squared errors and alpha  
+fn fit_exponential_smoothing(series: &[f32], alpha: f32) -> (f32, f32, f32) {  
+    let mut level = series[0];  
+    let mut sse = 0.0;  
+
+    for value in &series[1..] {  
+        sse += (value - level).powi(2);  
+        level = alpha * value + (1.0 - alpha) * level;  
+    }  
+
+    (level, sse, alpha)  
+}  
+
+struct HoltWintersFit {  
+    level: f32,  
+    trend: f32,  
+    seasonals: Vec<f32>,  
+    sse: f32,  
+    alpha: f32,  
+    beta: f32,  
+    gamma: f32,  
+}  
+
+fn holt_winters(series: &[f32], seasons: &[usize], horizon: usize, season_length: usize) -> Forecast {  
+    let mut best: Option<HoltWintersFit> = None;  
+
+    for alpha in SMOOTHING_GRID.iter() {  
+        for beta in SMOOTHING_GRID.iter() {  
+            for gamma in SMOOTHING_GRID.iter() {  
+                let fit = fit_holt_winters(series, seasons, season_length, *alpha, *beta, *gamma);  
+                if best.as_ref().is_none_or(|b| fit.sse < b.sse) {  
+                    best = Some(fit);  
+                }  
+            }  
+        }  
+    }  
+
+    let fit = best.unwrap();  
+    let n = series.len();  
+    let sigma = (fit.sse / (n - season_length) as f32).sqrt();  
+
+    // Variance multipliers for the additive model (Hyndman et al., 2008, table 6.1)  
+    let mut variance_factor: f32 = 1.0;  
+    let mut std_errors = vec![];  
+    for h in 1..=horizon {  
+        std_errors.push(sigma * variance_factor.sqrt());  
+        let seasonal = if h % season_length == 0 { fit.gamma * (1.0 - fit.alpha) } else { 0.0 };  
+        variance_factor += (fit.alpha * (1.0 + h as f32 * fit.beta) + seasonal).powi(2);  
+    }  
+
+    Forecast {  
+        values: (1..=horizon)  
+            .map(|h| fit.level + h as f32 * fit.trend + fit.seasonals[seasons[n + h - 1]])  
+            .collect(),  
+        std_errors,  
+    }  
+}  
+// Additive Holt-Winters; seasonals[s] holds the seasonal term for season s  
+fn fit_holt_winters(series: &[f32], seasons: &[usize], m: usize, alpha: f32, beta: f32, gamma: f32) -> HoltWintersFit {  
+    let first_mean = series[..m].iter().sum::<f32>() / m as f32;  
+    let second_mean = series[m..2 * m].iter().sum::<f32>() / m as f32;  
+
+    let mut level = first_mean;  
+    let mut trend = (second_mean - first_mean) / m as f32;  
+
+    // A season seen twice in the first m periods (after a week 53) starts from their mean  
+    let mut totals = vec![(0.0, 0); m];  
+    for (value, season) in series[..m].iter().zip(seasons) {  
+        totals[*season].0 += value - first_mean;  
+        totals[*season].1 += 1;  
+    }  
+    let mut seasonals: Vec<f32> = totals.iter().map(|(total, count)| total / (*count).max(1) as f32).collect();  
+    let mut sse = 0.0;  
+
+    for (t, value) in series.iter().enumerate().skip(m) {  
+        let season = seasonals[seasons[t]];  
+        let predicted = level + trend + season;  
+        sse += (value - predicted).powi(2);  
+
+        let previous_level = level;  
+        level = alpha * (value - season) + (1.0 - alpha) * (level + trend);  
+        trend = beta * (level - previous_level) + (1.0 - beta) * trend;  
+        seasonals[seasons[t]] = gamma * (value - level) + (1.0 - gamma) * season;  
+    }  
+
+    HoltWintersFit {  
+        level,  
+        trend,  
+        seasonals,  
+        sse,  
+        alpha,  
+        beta,  
+        gamma,  
+    }  
+}  
+
+#[cfg(test)]  
+mod tests {  
+    use super::*;  
+
+    // Seasons follow the position in the series  
+    fn forecast(series: &[f32], horizon: usize, season_length: usize, method: Method) -> Forecast {  
+        let seasons: Vec<usize> = (0..series.len() + horizon).map(|t| t % season_length).collect();  
+        forecast_seasons(series, &seasons, horizon, season_length, method)  
+    }  
+
+    fn seasonal_series(seasons: usize, season_length: usize) -> Vec<f32> {  
+        (0..seasons * season_length).map(|t| 10.0 + (t % season_length) as f32).collect()  
+    }  
+
+    #[test]  
+    fn seasonal_naive_repeats_the_last_season() {  
+        let mut series = seasonal_series(2, 4);  
+        series[4..].copy_from_slice(&[20.0, 21.0, 22.0, 23.0]);  
+
+        let result = forecast(&series, 6, 4, Method::SeasonalNaive);  
+        assert_eq!(result.values, vec![20.0, 21.0, 22.0, 23.0, 20.0, 21.0]);  
+        // The second season ahead is one more seasonal step of uncertainty  
+        assert!(result.std_errors[4] > result.std_errors[3]);  
+    }  
+
+    #[test]  
+    fn exponential_smoothing_of_a_constant_series_is_flat_and_certain() {  
+        let result = forecast(&[7.5; 20], 5, 12, Method::ExponentialSmoothing);  
+        assert_eq!(result.values, vec![7.5; 5]);  
+        assert_eq!(result.std_errors, vec![0.0; 5]);  
+    }  
+
+    #[test]  
+    fn holt_winters_falls_back_to_exponential_smoothing_without_two_seasons() {  
+        let series = seasonal_series(1, 12);  
+        let series = [&series[..], &series[..6]].concat();  
+
+        let holt_winters = forecast(&series, 4, 12, Method::HoltWinters);  
+        let smoothing = forecast(&series, 4, 12, Method::ExponentialSmoothing);  
+        assert_eq!(holt_winters.values, smoothing.values);  
+        assert_eq!(holt_winters.std_errors, smoothing.std_errors);  
+    }  
+
+    #[test]  
+    fn holt_winters_follows_a_repeating_season() {  
+        let series = seasonal_series(4, 12);  
+        let result = forecast(&series, 12, 12, Method::HoltWinters);  
+        for (predicted, expected) in result.values.iter().zip(&series[..12]) {  
+            assert!((predicted - expected).abs() < 0.5, "{} vs {}", predicted, expected);  
+        }  
+    }  
+
+    #[test]  
+    fn calendar_seasons_keep_the_forecast_aligned_after_a_long_year() {  
+        // Years of four periods, where the second year has a fifth period sharing the last  
+        // season, as ISO week 53 shares week 52's  
+        let seasons = [0, 1, 2, 3, 0, 1, 2, 3, 3, 0, 1, 2, 3, 0];  
+        let series: Vec<f32> = seasons[..10].iter().map(|s| 10.0 * *s as f32).collect();  
+
+        let by_calendar = forecast_seasons(&series, &seasons, 4, 4, Method::SeasonalNaive);  
+        assert_eq!(by_calendar.values, vec![10.0, 20.0, 30.0, 0.0]);  
+        assert_eq!(by_calendar.std_errors[0], 0.0);  
+
+        let by_position = forecast(&series, 4, 4, Method::SeasonalNaive);  
+        assert_ne!(by_position.values, by_calendar.values);  
+
+        let holt_winters = forecast_seasons(&series, &seasons, 4, 4, Method::HoltWinters);  
+        for (predicted, expected) in holt_winters.values.iter().zip(&by_calendar.values) {  
+            assert!((predicted - expected).abs() < 3.0, "{:?}", holt_winters.values);  
+        }  
+    }  
+
+    #[test]  
+    fn z_score_matches_the_normal_quantiles() {  
+        assert!((z_score(0.95) - 1.959_964).abs() < 1e-3);  
+        assert!((z_score(0.8) - 1.281_552).abs() < 1e-3);  
+        assert!((z_score(0.99) - 2.575_829).abs() < 1e-3);  
+    }  
+}
\ No newline at end of file
@@ -0,0 +1,188 @@
+// src/impute.rs  
+
+// Gap filling for a city's daily readings before they are aggregated into periods  
+
+use chrono::{Datelike, NaiveDate};  
+
+#[derive(Debug, Clone, Copy, PartialEq)]  
+pub enum Strategy {  
+    // Leave gaps empty so that period means are taken over the observed days only  
+    Drop,  
+    Zero,  
+    ForwardFill,  
+    Linear,  
+    // Mean of the observed values for the same calendar month in other years  
+    SeasonalMean,  
+}  
+
+impl Strategy {  
+    pub fn parse(strategy: &str) -> Option<Strategy> {  
+        match strategy.trim().to_lowercase().as_str() {  
+            "" | "drop" => Some(Strategy::Drop),  
+            "zero" => Some(Strategy::Zero),  
+            "ffill" | "forward_fill" => Some(Strategy::ForwardFill),  
+            "linear" => Some(Strategy::Linear),  
+            "seasonal_mean" => Some(Strategy::SeasonalMean),  
+            _ => None,  
+        }  
+    }  
+
+    pub fn name(&self) -> &'static str {  
+        match self {  
+            Strategy::Drop => "drop",  
+            Strategy::Zero => "zero",  
+            Strategy::ForwardFill => "forward_fill",  
+            Strategy::Linear => "linear",  
+            Strategy::SeasonalMean => "seasonal_mean",  
+        }  
+    }  
+}  
+
+// `values` and `dates` are parallel and need not be sorted. Gaps that cannot be  
+// filled (e.g. before the first reading when forward-filling) stay empty  
+pub fn impute(values: &[Option<f32>], dates: &[NaiveDate], strategy: Strategy) -> Vec<Option<f32>> {  
+    let mut order: Vec<usize> = (0..values.len()).collect();  
+    order.sort_by_key(|&i| dates[i]);  
+
+    let mut filled = values.to_vec();  
+
+    match strategy {  
+        Strategy::Drop => {}  
+        Strategy::Zero => {  
+            for value in filled.iter_mut() {  
+                *value = Some(value.unwrap_or(0.0));  
+            }  
+        }  
+        Strategy::ForwardFill => {  
+            let mut previous = None;  
+            for &i in &order {  
+                match values[i] {  
+                    Some(value) => previous = Some(value),  
+                    None => filled[i] = previous,  
+                }  
+            }  
+        }  
+        Strategy::Linear => {  
+            // Interpolates each run of gaps once the reading that closes it is reached  
+            let mut previous: Option<usize> = None;  
+            let mut gap: Vec<usize> = vec![];  
+            for &i in &order {  
+                match values[i] {  
+                    Some(end_value) => {  
+                        if let Some(start) = previous {  
+                            let start_value = values[start].unwrap();  
+                            let span = (dates[i] - dates[start]).num_days() as f32;  
+                            for &g in &gap {  
+                                let t = if span > 0.0 {  
+                                    (dates[g] - dates[start]).num_days() as f32 / span  
+                                } else {  
+                                    0.0  
+                                };  
+                                filled[g] = Some(start_value + t * (end_value - start_value));  
+                            }  
+                        }  
+                        gap.clear();  
+                        previous = Some(i);  
+                    }  
+                    None => gap.push(i),  
+                }  
+            }  
+        }  
+        Strategy::SeasonalMean => {  
+            let mut sums = [(0.0, 0); 12];  
+            for (value, date) in values.iter().zip(dates) {  
+                if let Some(value) = value {  
+                    let month = date.month0() as usize;  
+                    sums[month].0 += value;  
+                    sums[month].1 += 1;  
+                }  
+            }  
+
+            for (i, value) in filled.iter_mut().enumerate() {  
+                let (sum, count) = sums[dates[i].month0() as usize];  
+                if value.is_none() && count > 0 {  
+                    *value = Some(sum / count as f32);  
+                }  
+            }  
+        }  
+    }  
+
+    filled  
+}  
+
+#[cfg(test)]  
+mod tests {  
+    use super::*;  
+
+    fn day(month: u32, day: u32) -> NaiveDate {  
+        NaiveDate::from_ymd_opt(2020, month, day).unwrap()  
+    }  
+
+    // Deliberately out of date order: 1, 2, 3, 4 and 5 January  
+    fn january() -> (Vec<Option<f32>>, Vec<NaiveDate>) {  
+        (  
+            vec![Some(4.0), None, Some(1.0), None, None],  
+            vec![day(1, 4), day(1, 1), day(1, 2), day(1, 3), day(1, 5)],  
+        )  
+    }  
+
+    #[test]  
+    fn parse_accepts_names_and_aliases() {  
+        assert_eq!(Strategy::parse(""), Some(Strategy::Drop));  
+        assert_eq!(Strategy::parse(" FFill "), Some(Strategy::ForwardFill));  
+        assert_eq!(Strategy::parse("seasonal_mean"), Some(Strategy::SeasonalMean));  
+        assert_eq!(Strategy::parse("median"), None);  
+        assert_eq!(Strategy::parse(Strategy::Linear.name()), Some(Strategy::Linear));  
+    }  
+
+    #[test]  
+    fn drop_leaves_gaps_empty() {  
+        let (values, dates) = january();  
+        assert_eq!(impute(&values, &dates, Strategy::Drop), values);  
+    }  
+
+    #[test]  
+    fn zero_fills_every_gap() {  
+        let (values, dates) = january();  
+        assert_eq!(  
+            impute(&values, &dates, Strategy::Zero),  
+            vec![Some(4.0), Some(0.0), Some(1.0), Some(0.0), Some(0.0)]  
+        );  
+    }  
+
+    #[test]  
+    fn forward_fill_follows_date_order_and_leaves_leading_gaps() {  
+        let (values, dates) = january();  
+        assert_eq!(  
+            impute(&values, &dates, Strategy::ForwardFill),  
+            vec![Some(4.0), None, Some(1.0), Some(1.0), Some(4.0)]  
+        );  
+    }  
+
+    #[test]  
+    fn linear_interpolates_by_date_and_leaves_open_ends() {  
+        let values = vec![Some(0.0), None, None, Some(6.0), None];  
+        let dates = vec![day(1, 1), day(1, 2), day(1, 4), day(1, 7), day(1, 8)];  
+        assert_eq!(  
+            impute(&values, &dates, Strategy::Linear),  
+            vec![Some(0.0), Some(1.0), Some(3.0), Some(6.0), None]  
+        );  
+    }  
+
+    #[test]  
+    fn seasonal_mean_uses_the_same_calendar_month() {  
+        let values = vec![Some(10.0), Some(20.0), Some(30.0), None, None, None];  
+        let dates = vec![  
+            NaiveDate::from_ymd_opt(2018, 1, 5).unwrap(),  
+            NaiveDate::from_ymd_opt(2019, 1, 5).unwrap(),  
+            NaiveDate::from_ymd_opt(2019, 7, 5).unwrap(),  
+            NaiveDate::from_ymd_opt(2020, 1, 5).unwrap(),  
+            NaiveDate::from_ymd_opt(2020, 7, 5).unwrap(),  
+            NaiveDate::from_ymd_opt(2020, 3, 5).unwrap(),  
+        ];  
+        assert_eq!(  
+            impute(&values, &dates, Strategy::SeasonalMean),  
+            vec![Some(10.0), Some(20.0), Some(30.0), Some(15.0), Some(30.0), None]  
+        );  
+    }  
+}
\ No newline at end of file
@@ -2,18 +2,22 @@
 
 use marine_rs_sdk::marine;  
 use marine_rs_sdk::module_manifest;  
-use marine_rs_sdk::MountedBinaryResult;  
 
 module_manifest!();  
 
 pub fn main() {}  
 
+mod decompose;  
+mod forecast;  
+mod impute;  
+
 use serde::{Deserialize, Serialize};  
+use std::collections::BTreeMap;  
 use std::env;  
 use std::fs;  
 use std::path::Path;  
 
-use chrono::{Datelike, NaiveDate};  
+use chrono::{Datelike, Duration, NaiveDate, Weekday};  
 use polars::prelude::*;  
 use reqwest::Client;  
 use std::error::Error;  
@@ -24,87 +28,1287 @@ use log::info;
 #[marine]  
 #[derive(Debug, Serialize, Deserialize)]  
 pub struct PredictionReport {  
+    error: String,  
+    // Last observed period the forecast starts from, and the label of each forecast step  
+    forecast_origin: String,  
+    periods: Vec<String>,  
     min_temp: Vec<f32>,  
+    max_temp: Vec<f32>,  
     wind_direction: Vec<String>,  
     wind_speed: Vec<f32>,  
     rainfall: Vec<f32>,  
+    confidence_level: f32,  
+    min_temp_lower: Vec<f32>,  
+    min_temp_upper: Vec<f32>,  
+    max_temp_lower: Vec<f32>,  
+    max_temp_upper: Vec<f32>,  
+    wind_speed_lower: Vec<f32>,  
+    wind_speed_upper: Vec<f32>,  
+    rainfall_lower: Vec<f32>,  
+    rainfall_upper: Vec<f32>,  
+    llm_output_status: String,  
+    llm_errors: Vec<String>,  
+    imputation: String,  
+    imputed: Vec<ImputationCount>,  
+}  
+
+#[marine]  
+#[derive(Debug, Clone, Serialize, Deserialize)]  
+pub struct ImputationCount {  
+    column: String,  
+    // Daily readings that were missing, and how many of those the strategy filled  
+    missing: u32,  
+    imputed: u32,  
+}  
+
+#[marine]  
+#[derive(Debug, Serialize, Deserialize)]  
+pub struct ForecastAccuracy {  
+    metric: String,  
+    mae: f32,  
+    rmse: f32,  
+    mape: Option<f32>,  
+    bias: f32,  
+    observations: u32,  
+}  
+
+#[marine]  
+#[derive(Debug, Serialize, Deserialize)]  
+pub struct BacktestReport {  
+    error: String,  
+    method: String,  
+    granularity: String,  
+    holdout_periods: u32,  
+    training_periods: u32,  
+    metrics: Vec<ForecastAccuracy>,  
+    imputation: String,  
+    imputed: Vec<ImputationCount>,  
 }  
 
 #[marine]  
-pub fn generate_prediction_weather_report(city: String) -> PredictionReport {  
+#[derive(Debug, Serialize, Deserialize)]  
+pub struct EnsembleMember {  
+    method: String,  
+    weight: f32,  
+    error: String,  
+    report: PredictionReport,  
+}  
+
+#[marine]  
+#[derive(Debug, Serialize, Deserialize)]  
+pub struct EnsembleReport {  
+    error: String,  
+    weighting: String,  
+    blend: PredictionReport,  
+    members: Vec<EnsembleMember>,  
+}  
+
+#[marine]  
+#[derive(Debug, Serialize, Deserialize)]  
+pub struct TrendReport {  
+    error: String,  
+    city: String,  
+    metric: String,  
+    periods: Vec<String>,  
+    observed: Vec<f32>,  
+    trend: Vec<f32>,  
+    seasonal: Vec<f32>,  
+    residual: Vec<f32>,  
+    // Seasonal Sen's slope, in metric units per year  
+    slope_per_year: f32,  
+    // Value of the Sen line at the first period, for drawing it over the series  
+    intercept: f32,  
+    // Seasonal (month-by-month) Mann-Kendall statistic and its two-sided p-value  
+    mann_kendall_z: f32,  
+    p_value: f32,  
+    significant: bool,  
+    imputation: String,  
+    imputed: Vec<ImputationCount>,  
+}  
+
+const DEFAULT_CONFIDENCE_LEVEL: f32 = 0.95;  
+const DEFAULT_HORIZON: usize = 12;  
+const MAX_REPAIR_ATTEMPTS: usize = 2;  
+const DEFAULT_LLM_API_URL: &str = "https://api.example.com/v1/chat/completions"; // Replace with actual API endpoint  
+const DEFAULT_TREND_METRIC: &str = "MaxTemp";  
+const TREND_SIGNIFICANCE: f32 = 0.05;  
+const DEFAULT_ENSEMBLE: [&str; 4] = ["llm", "seasonal_naive", "exponential_smoothing", "holt_winters"];  
+
+const COMPASS_POINTS: [&str; 16] = [  
+    "N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE", "S", "SSW", "SW", "WSW", "W", "WNW", "NW", "NNW",  
+];  
+
+#[marine]  
+pub fn generate_prediction_weather_report(  
+    city: String,  
+    method: String,  
+    confidence_level: f32,  
+    horizon: u32,  
+    granularity: String,  
+    imputation: String,  
+) -> PredictionReport {  
     let file_path = "./weatherAUS.csv";  
     let system_prompt_path = "./system_prompt1.txt";  
 
     // Initialize logging (optional)  
     let _ = env_logger::try_init();  
 
+    // A zero horizon keeps the original 12 steps  
+    let horizon = if horizon == 0 { DEFAULT_HORIZON } else { horizon as usize };  
+
+    let failed_report = |error: String| PredictionReport {  
+        error,  
+        ..empty_prediction_report()  
+    };  
+
+    // An empty method keeps the original LLM behaviour  
+    let method = match parse_forecast_method(&method) {  
+        Some(method) => method,  
+        None => return failed_report(format!("Unknown forecasting method: {}", method)),  
+    };  
+
+    let granularity = match Granularity::parse(&granularity) {  
+        Some(granularity) => granularity,  
+        None => return failed_report(format!("Unknown granularity: {}", granularity)),  
+    };  
+
+    let confidence_level = match parse_confidence_level(confidence_level) {  
+        Some(level) => level,  
+        None => {  
+            return failed_report(format!(  
+                "Invalid confidence level: {}. Expected a value between 0 and 1 or between 50 and 100.",  
+                confidence_level  
+            ))  
+        }  
+    };  
+
+    let strategy = match impute::Strategy::parse(&imputation) {  
+        Some(strategy) => strategy,  
+        None => return failed_report(format!("Unknown imputation strategy: {}", imputation)),  
+    };  
+
     info!("Loading data...");  
-    let df = match load_weather_data(file_path) {  
-        Ok(df) => df,  
-        Err(e) => {  
-            info!("Error loading weather data: {}", e);  
-            return empty_prediction_report();  
+    let (city, filtered_df, imputed) = match prepare_city_data(file_path, &city, granularity, strategy) {  
+        Ok(data) => data,  
+        Err(e) => return failed_report(e),  
+    };  
+
+    match run_forecast(  
+        &filtered_df,  
+        &city,  
+        &method,  
+        horizon,  
+        granularity,  
+        confidence_level,  
+        system_prompt_path,  
+    ) {  
+        // Reported like any other failure, without the zero-filled series behind it  
+        Ok(report) if report.llm_output_status == "defaulted" => {  
+            let error = format!("The LLM did not return a usable forecast. {}", report.llm_errors.join(" "));  
+            PredictionReport {  
+                llm_output_status: report.llm_output_status,  
+                llm_errors: report.llm_errors,  
+                imputation: strategy.name().to_string(),  
+                imputed,  
+                ..failed_report(error)  
+            }  
         }  
+        Ok(report) => PredictionReport {  
+            imputation: strategy.name().to_string(),  
+            imputed,  
+            ..report  
+        },  
+        Err(e) => failed_report(e),  
+    }  
+}  
+
+#[marine]  
+pub fn backtest_forecast(  
+    city: String,  
+    method: String,  
+    holdout_periods: u32,  
+    granularity: String,  
+    imputation: String,  
+) -> BacktestReport {  
+    let file_path = "./weatherAUS.csv";  
+    let system_prompt_path = "./system_prompt1.txt";  
+
+    let _ = env_logger::try_init();  
+
+    let failed_backtest = |error: String| BacktestReport {  
+        error,  
+        method: method.clone(),  
+        granularity: granularity.clone(),  
+        holdout_periods,  
+        training_periods: 0,  
+        metrics: vec![],  
+        imputation: imputation.clone(),  
+        imputed: vec![],  
+    };  
+
+    let forecast_method = match parse_forecast_method(&method) {  
+        Some(forecast_method) => forecast_method,  
+        None => return failed_backtest(format!("Unknown forecasting method: {}", method)),  
+    };  
+
+    let period = match Granularity::parse(&granularity) {  
+        Some(period) => period,  
+        None => return failed_backtest(format!("Unknown granularity: {}", granularity)),  
+    };  
+
+    if holdout_periods == 0 {  
+        return failed_backtest("holdout_periods must be at least 1".to_string());  
+    }  
+
+    let strategy = match impute::Strategy::parse(&imputation) {  
+        Some(strategy) => strategy,  
+        None => return failed_backtest(format!("Unknown imputation strategy: {}", imputation)),  
+    };  
+
+    info!("Loading data...");  
+    let (city, daily_df) = match load_city_daily_data(file_path, &city) {  
+        Ok(data) => data,  
+        Err(e) => return failed_backtest(e),  
+    };  
+    let imputed = match aggregate_city_data(&daily_df, &city, period, strategy) {  
+        Ok((_, imputed)) => imputed,  
+        Err(e) => return failed_backtest(e),  
     };  
 
-    // Fill NaN values with zero  
-    let df_filled = df.fill_null(FillNullStrategy::Zero).unwrap_or(df);  
+    info!("Backtesting {} over the last {} {}s...", method, holdout_periods, period.name());  
+    match run_backtest(  
+        &daily_df,  
+        &city,  
+        &forecast_method,  
+        holdout_periods as usize,  
+        period,  
+        strategy,  
+        system_prompt_path,  
+    ) {  
+        Ok((training_periods, metrics)) => BacktestReport {  
+            error: "".to_string(),  
+            method: method.clone(),  
+            granularity: period.name().to_string(),  
+            holdout_periods,  
+            training_periods: training_periods as u32,  
+            metrics,  
+            imputation: strategy.name().to_string(),  
+            imputed,  
+        },  
+        Err(e) => failed_backtest(e),  
+    }  
+}  
+
+#[marine]  
+pub fn generate_ensemble_weather_report(  
+    city: String,  
+    methods: Vec<String>,  
+    weighting: String,  
+    confidence_level: f32,  
+    horizon: u32,  
+    granularity: String,  
+    imputation: String,  
+) -> EnsembleReport {  
+    let file_path = "./weatherAUS.csv";  
+    let system_prompt_path = "./system_prompt1.txt";  
+
+    let _ = env_logger::try_init();  
+
+    let horizon = if horizon == 0 { DEFAULT_HORIZON } else { horizon as usize };  
 
-    let filtered_df = get_city_month_data(&df_filled, &city);  
+    let failed_ensemble = |error: String| EnsembleReport {  
+        error,  
+        weighting: weighting.clone(),  
+        blend: empty_prediction_report(),  
+        members: vec![],  
+    };  
 
-    let prompt = generate_prompt(&filtered_df, &city);  
+    // An empty member list runs the LLM alongside every local model  
+    let methods: Vec<String> = if methods.is_empty() {  
+        DEFAULT_ENSEMBLE.iter().map(|m| m.to_string()).collect()  
+    } else {  
+        methods  
+    };  
 
-    if prompt.starts_with("No data available") {  
-        info!("{}", prompt);  
-        return empty_prediction_report();  
+    let mut forecast_methods = vec![];  
+    for method in &methods {  
+        match parse_forecast_method(method) {  
+            Some(forecast_method) => forecast_methods.push(forecast_method),  
+            None => return failed_ensemble(format!("Unknown forecasting method: {}", method)),  
+        }  
     }  
 
-    info!("Calling API...");  
-    let report = match call_api(&prompt, system_prompt_path) {  
-        Ok(report) => report,  
-        Err(e) => {  
-            info!("Error in API call: {}", e);  
-            "".to_string()  
+    let weighting = match weighting.trim() {  
+        "" | "equal" => "equal",  
+        "backtest" => "backtest",  
+        other => return failed_ensemble(format!("Unknown weighting: {}", other)),  
+    };  
+
+    let period = match Granularity::parse(&granularity) {  
+        Some(period) => period,  
+        None => return failed_ensemble(format!("Unknown granularity: {}", granularity)),  
+    };  
+
+    let confidence_level = match parse_confidence_level(confidence_level) {  
+        Some(level) => level,  
+        None => {  
+            return failed_ensemble(format!(  
+                "Invalid confidence level: {}. Expected a value between 0 and 1 or between 50 and 100.",  
+                confidence_level  
+            ))  
         }  
     };  
 
-    // Parse the API response  
-    let (parsed_temp, parsed_wind_direction, parsed_wind_speed, parsed_rainfall) = parse_input(&report);  
+    let strategy = match impute::Strategy::parse(&imputation) {  
+        Some(strategy) => strategy,  
+        None => return failed_ensemble(format!("Unknown imputation strategy: {}", imputation)),  
+    };  
+
+    info!("Loading data...");  
+    let (city, daily_df) = match load_city_daily_data(file_path, &city) {  
+        Ok(data) => data,  
+        Err(e) => return failed_ensemble(e),  
+    };  
+    let (filtered_df, imputed) = match aggregate_city_data(&daily_df, &city, period, strategy) {  
+        Ok(data) => data,  
+        Err(e) => return failed_ensemble(e),  
+    };  
+
+    let mut members: Vec<EnsembleMember> = methods  
+        .iter()  
+        .zip(&forecast_methods)  
+        .map(|(method, forecast_method)| {  
+            info!("Running ensemble member {}...", method);  
+            let (report, error) = match run_forecast(  
+                &filtered_df,  
+                &city,  
+                forecast_method,  
+                horizon,  
+                period,  
+                confidence_level,  
+                system_prompt_path,  
+            ) {  
+                Ok(report) if report.llm_output_status == "defaulted" => {  
+                    let error = report.llm_errors.join(" ");  
+                    let report = PredictionReport {  
+                        llm_output_status: report.llm_output_status,  
+                        llm_errors: report.llm_errors,  
+                        ..empty_prediction_report()  
+                    };  
+                    (report, error)  
+                }  
+                Ok(report) => (report, "".to_string()),  
+                Err(e) => (empty_prediction_report(), e),  
+            };  
+            EnsembleMember {  
+                method: method.clone(),  
+                weight: 0.0,  
+                error,  
+                report,  
+            }  
+        })  
+        .collect();  
+
+    let weights = if weighting == "backtest" {  
+        // Hold out as many periods as are forecast, capped so at least half the history is kept for fitting  
+        let available = get_period_index(&filtered_df, period).len();  
+        let holdout = horizon.min(available / 2);  
+        let scores: Vec<Option<Vec<f32>>> = members  
+            .iter()  
+            .zip(&forecast_methods)  
+            .map(|(member, forecast_method)| {  
+                if !member.error.is_empty() || holdout == 0 {  
+                    return None;  
+                }  
+                info!("Backtesting ensemble member {}...", member.method);  
+                run_backtest(&daily_df, &city, forecast_method, holdout, period, strategy, system_prompt_path)  
+                    .ok()  
+                    .map(|(_, metrics)| metrics.iter().map(|m| m.rmse).collect())  
+            })  
+            .collect();  
+        backtest_weights(&scores)  
+    } else {  
+        members  
+            .iter()  
+            .map(|member| if member.error.is_empty() { 1.0 } else { 0.0 })  
+            .collect()  
+    };  
+
+    let total: f32 = weights.iter().sum();  
+    if total <= 0.0 {  
+        let errors: Vec<String> = members  
+            .iter()  
+            .filter(|m| !m.error.is_empty())  
+            .map(|m| format!("{}: {}", m.method, m.error))  
+            .collect();  
+        return EnsembleReport {  
+            error: format!("No ensemble member produced a usable forecast. {}", errors.join(" ")),  
+            weighting: weighting.to_string(),  
+            blend: empty_prediction_report(),  
+            members,  
+        };  
+    }  
+
+    for (member, weight) in members.iter_mut().zip(&weights) {  
+        member.weight = weight / total;  
+    }  
+
+    // Every member is fitted on the same imputed history, so only the blend records it  
+    EnsembleReport {  
+        error: "".to_string(),  
+        weighting: weighting.to_string(),  
+        blend: PredictionReport {  
+            imputation: strategy.name().to_string(),  
+            imputed,  
+            ..blend_reports(&members, horizon, confidence_level)  
+        },  
+        members,  
+    }  
+}  
+
+#[marine]  
+pub fn decompose_weather_trend(city: String, metric: String, imputation: String) -> TrendReport {  
+    let file_path = "./weatherAUS.csv";  
+
+    let _ = env_logger::try_init();  
+
+    let metric = if metric.trim().is_empty() {  
+        DEFAULT_TREND_METRIC.to_string()  
+    } else {  
+        metric.trim().to_string()  
+    };  
+
+    let failed_trend = |error: String| TrendReport {  
+        error,  
+        city: city.clone(),  
+        metric: metric.clone(),  
+        periods: vec![],  
+        observed: vec![],  
+        trend: vec![],  
+        seasonal: vec![],  
+        residual: vec![],  
+        slope_per_year: 0.0,  
+        intercept: 0.0,  
+        mann_kendall_z: 0.0,  
+        p_value: 1.0,  
+        significant: false,  
+        imputation: imputation.clone(),  
+        imputed: vec![],  
+    };  
+
+    let strategy = match impute::Strategy::parse(&imputation) {  
+        Some(strategy) => strategy,  
+        None => return failed_trend(format!("Unknown imputation strategy: {}", imputation)),  
+    };  
+
+    // Trends are read off the monthly aggregates  
+    let period = Granularity::Monthly;  
+
+    info!("Loading data...");  
+    let (city, filtered_df, imputed) = match prepare_city_data(file_path, &city, period, strategy) {  
+        Ok(data) => data,  
+        Err(e) => return failed_trend(e),  
+    };  
+
+    if filtered_df.column(&metric).is_err() {  
+        return failed_trend(format!("Unknown metric: {}", metric));  
+    }  
+
+    let observed = get_period_series(&filtered_df, &metric, period);  
+    let season_length = period.season_length();  
+    if observed.len() < 2 * season_length {  
+        return failed_trend(format!(  
+            "Not enough history for {}: {} {}s available, at least {} needed.",  
+            city,  
+            observed.len(),  
+            period.name(),  
+            2 * season_length  
+        ));  
+    }  
+
+    info!("Decomposing {} for {}...", metric, city);  
+    let decomposition = decompose::decompose(&observed, season_length);  
+
+    // Copies carried into the gaps would count as ties, so the test sees them as missing  
+    let gaps: Vec<f32> = get_period_values(&filtered_df, &metric, period)  
+        .into_iter()  
+        .map(|value| value.unwrap_or(f32::NAN))  
+        .collect();  
+    let test = decompose::trend_test(&gaps, season_length);  
+
+    TrendReport {  
+        error: "".to_string(),  
+        city,  
+        metric,  
+        periods: get_period_index(&filtered_df, period)  
+            .into_iter()  
+            .map(|start| period.label(start))  
+            .collect(),  
+        observed,  
+        trend: decomposition.trend,  
+        seasonal: decomposition.seasonal,  
+        residual: decomposition.residual,  
+        slope_per_year: test.slope * season_length as f32,  
+        intercept: test.intercept,  
+        mann_kendall_z: test.z,  
+        p_value: test.p_value,  
+        significant: test.p_value < TREND_SIGNIFICANCE,  
+        imputation: strategy.name().to_string(),  
+        imputed,  
+    }  
+}  
+
+// Fits on all but the last `holdout` periods and scores the forecast of that tail;  
+// returns the number of training periods with the per-metric accuracy. The training  
+// periods are imputed from the training days alone, so the holdout cannot leak into them  
+fn run_backtest(  
+    daily_df: &DataFrame,  
+    city: &str,  
+    method: &ForecastMethod,  
+    holdout: usize,  
+    period: Granularity,  
+    strategy: impute::Strategy,  
+    system_prompt_path: &str,  
+) -> Result<(usize, Vec<ForecastAccuracy>), String> {  
+    let (df, _) = aggregate_city_data(daily_df, city, period, strategy)?;  
+    let periods = get_period_index(&df, period);  
+    if periods.len() <= holdout + 1 {  
+        return Err(format!(  
+            "Not enough history for {}: {} {}s available, {} held out.",  
+            city,  
+            periods.len(),  
+            period.name(),  
+            holdout  
+        ));  
+    }  
+
+    let training_periods = periods.len() - holdout;  
+    let cutoff = periods[training_periods - 1];  
+    let training_df = get_training_data(daily_df, city, cutoff, period, strategy)?;  
+
+    let predicted = run_forecast(  
+        &training_df,  
+        city,  
+        method,  
+        holdout,  
+        period,  
+        DEFAULT_CONFIDENCE_LEVEL,  
+        system_prompt_path,  
+    )?;  
+
+    if predicted.llm_output_status == "defaulted" {  
+        return Err(predicted.llm_errors.join(" "));  
+    }  
+
+    let actual = |column: &str| get_period_series(&df, column, period).split_off(training_periods);  
+
+    Ok((  
+        training_periods,  
+        vec![  
+            forecast_accuracy("MinTemp", &predicted.min_temp, &actual("MinTemp")),  
+            forecast_accuracy("MaxTemp", &predicted.max_temp, &actual("MaxTemp")),  
+            forecast_accuracy("WindGustSpeed", &predicted.wind_speed, &actual("WindGustSpeed")),  
+            forecast_accuracy("Rainfall", &predicted.rainfall, &actual("Rainfall")),  
+        ],  
+    ))  
+}  
+
+// Each member is scored by its RMSE relative to the other members on every metric,  
+// so metrics on different scales count equally; the weight is the inverse of that score  
+fn backtest_weights(scores: &[Option<Vec<f32>>]) -> Vec<f32> {  
+    let scored: Vec<&Vec<f32>> = scores.iter().flatten().collect();  
+    let metric_count = scored.iter().map(|s| s.len()).min().unwrap_or(0);  
+    let metric_means: Vec<f32> = (0..metric_count)  
+        .map(|i| scored.iter().map(|s| s[i]).sum::<f32>() / scored.len() as f32)  
+        .collect();  
+
+    scores  
+        .iter()  
+        .map(|score| match score {  
+            Some(rmses) => {  
+                let relative: Vec<f32> = metric_means  
+                    .iter()  
+                    .zip(rmses)  
+                    .filter(|(mean, _)| **mean > 0.0)  
+                    .map(|(mean, rmse)| rmse / mean)  
+                    .collect();  
+                if relative.is_empty() {  
+                    return 1.0;  
+                }  
+                let score = relative.iter().sum::<f32>() / relative.len() as f32;  
+                if score > 0.0 {  
+                    1.0 / score  
+                } else {  
+                    // A perfect backtest would get an infinite weight, so cap it  
+                    1.0e6  
+                }  
+            }  
+            None => 0.0,  
+        })  
+        .collect()  
+}  
+
+// Weighted mean of the members' series and bounds; wind direction goes to the  
+// direction with the largest total weight at each step  
+fn blend_reports(members: &[EnsembleMember], horizon: usize, confidence_level: f32) -> PredictionReport {  
+    let weighted = members.iter().filter(|m| m.weight > 0.0);  
+    let blend = |series: fn(&PredictionReport) -> &Vec<f32>| -> Vec<f32> {  
+        (0..horizon)  
+            .map(|i| {  
+                weighted  
+                    .clone()  
+                    .map(|m| m.weight * series(&m.report).get(i).copied().unwrap_or(0.0))  
+                    .sum()  
+            })  
+            .collect()  
+    };  
+
+    let wind_direction = (0..horizon)  
+        .map(|i| {  
+            let mut votes: BTreeMap<String, f32> = BTreeMap::new();  
+            for member in weighted.clone() {  
+                if let Some(direction) = member.report.wind_direction.get(i).filter(|d| !d.is_empty()) {  
+                    *votes.entry(direction.clone()).or_insert(0.0) += member.weight;  
+                }  
+            }  
+            votes  
+                .into_iter()  
+                .fold(None, |best: Option<(String, f32)>, (direction, weight)| match best {  
+                    Some(best) if best.1 >= weight => Some(best),  
+                    _ => Some((direction, weight)),  
+                })  
+                .map(|(direction, _)| direction)  
+                .unwrap_or_default()  
+        })  
+        .collect();  
+
+    // All members share the same history, so the labels of any of them will do  
+    let first = weighted.clone().next().map(|m| &m.report);  
 
     PredictionReport {  
-        min_temp: parsed_temp,  
-        wind_direction: parsed_wind_direction,  
-        wind_speed: parsed_wind_speed,  
-        rainfall: parsed_rainfall,  
+        error: "".to_string(),  
+        forecast_origin: first.map(|r| r.forecast_origin.clone()).unwrap_or_default(),  
+        periods: first.map(|r| r.periods.clone()).unwrap_or_else(|| vec!["".to_string(); horizon]),  
+        min_temp: blend(|r| &r.min_temp),  
+        max_temp: blend(|r| &r.max_temp),  
+        wind_direction,  
+        wind_speed: blend(|r| &r.wind_speed),  
+        rainfall: blend(|r| &r.rainfall),  
+        confidence_level,  
+        min_temp_lower: blend(|r| &r.min_temp_lower),  
+        min_temp_upper: blend(|r| &r.min_temp_upper),  
+        max_temp_lower: blend(|r| &r.max_temp_lower),  
+        max_temp_upper: blend(|r| &r.max_temp_upper),  
+        wind_speed_lower: blend(|r| &r.wind_speed_lower),  
+        wind_speed_upper: blend(|r| &r.wind_speed_upper),  
+        rainfall_lower: blend(|r| &r.rainfall_lower),  
+        rainfall_upper: blend(|r| &r.rainfall_upper),  
+        llm_output_status: "".to_string(),  
+        llm_errors: vec![],  
+        imputation: "".to_string(),  
+        imputed: vec![],  
+    }  
+}  
+
+enum ForecastMethod {  
+    Llm,  
+    Statistical(forecast::Method),  
+}  
+
+fn parse_forecast_method(method: &str) -> Option<ForecastMethod> {  
+    match method.trim() {  
+        "" | "llm" => Some(ForecastMethod::Llm),  
+        other => forecast::Method::parse(other).map(ForecastMethod::Statistical),  
+    }  
+}  
+
+#[derive(Debug, Clone, Copy, PartialEq)]  
+enum Granularity {  
+    Daily,  
+    Weekly,  
+    Monthly,  
+}  
+
+// Periods are identified by their first day: the date itself, the Monday of  
+// the ISO week, or the first of the month  
+impl Granularity {  
+    fn parse(granularity: &str) -> Option<Granularity> {  
+        match granularity.trim().to_lowercase().as_str() {  
+            "daily" | "day" => Some(Granularity::Daily),  
+            "weekly" | "week" => Some(Granularity::Weekly),  
+            "" | "monthly" | "month" => Some(Granularity::Monthly),  
+            _ => None,  
+        }  
+    }  
+
+    fn name(&self) -> &'static str {  
+        match self {  
+            Granularity::Daily => "day",  
+            Granularity::Weekly => "week",  
+            Granularity::Monthly => "month",  
+        }  
+    }  
+
+    fn column_name(&self) -> &'static str {  
+        match self {  
+            Granularity::Daily => "Day",  
+            Granularity::Weekly => "Year-Week",  
+            Granularity::Monthly => "Year-Month",  
+        }  
+    }  
+
+    fn season_length(&self) -> usize {  
+        match self {  
+            Granularity::Daily => 365,  
+            Granularity::Weekly => 52,  
+            Granularity::Monthly => 12,  
+        }  
+    }  
+
+    fn period_start(&self, date: NaiveDate) -> NaiveDate {  
+        match self {  
+            Granularity::Daily => date,  
+            Granularity::Weekly => date - Duration::days(date.weekday().num_days_from_monday() as i64),  
+            Granularity::Monthly => date.with_day(1).unwrap(),  
+        }  
+    }  
+
+    fn next(&self, start: NaiveDate) -> NaiveDate {  
+        match self {  
+            Granularity::Daily => start + Duration::days(1),  
+            Granularity::Weekly => start + Duration::days(7),  
+            Granularity::Monthly if start.month() == 12 => NaiveDate::from_ymd_opt(start.year() + 1, 1, 1).unwrap(),  
+            Granularity::Monthly => NaiveDate::from_ymd_opt(start.year(), start.month() + 1, 1).unwrap(),  
+        }  
+    }  
+
+    // Season of the period within the year, in 0..season_length, taken from the calendar  
+    // so that it matches the same season in past years. ISO week 53 shares week 52's season  
+    // and 29 February shares 28 February's  
+    fn season_index(&self, start: NaiveDate) -> usize {  
+        match self {  
+            Granularity::Daily => {  
+                let day = if start.month() == 2 { start.day().min(28) } else { start.day() };  
+                NaiveDate::from_ymd_opt(2001, start.month(), day).unwrap().ordinal0() as usize  
+            }  
+            Granularity::Weekly => start.iso_week().week().min(52) as usize - 1,  
+            Granularity::Monthly => start.month0() as usize,  
+        }  
+    }  
+
+    fn label(&self, start: NaiveDate) -> String {  
+        match self {  
+            Granularity::Daily => start.format("%Y-%m-%d").to_string(),  
+            Granularity::Weekly => format!("{}-W{:02}", start.iso_week().year(), start.iso_week().week()),  
+            Granularity::Monthly => start.format("%Y-%m").to_string(),  
+        }  
+    }  
+
+    fn parse_label(&self, label: &str) -> Option<NaiveDate> {  
+        match self {  
+            Granularity::Daily => NaiveDate::parse_from_str(label, "%Y-%m-%d").ok(),  
+            Granularity::Weekly => {  
+                let (year, week) = label.split_once("-W")?;  
+                NaiveDate::from_isoywd_opt(year.parse().ok()?, week.parse().ok()?, Weekday::Mon)  
+            }  
+            Granularity::Monthly => NaiveDate::parse_from_str(&format!("{}-01", label), "%Y-%m-%d").ok(),  
+        }  
+    }  
+}  
+
+// Loads the dataset and returns the resolved city name with its per-period aggregates  
+// and the number of daily values imputed in each column  
+fn prepare_city_data(  
+    file_path: &str,  
+    city: &str,  
+    granularity: Granularity,  
+    strategy: impute::Strategy,  
+) -> Result<(String, DataFrame, Vec<ImputationCount>), String> {  
+    let (city, daily_df) = load_city_daily_data(file_path, city)?;  
+    let (filtered_df, imputed) = aggregate_city_data(&daily_df, &city, granularity, strategy)?;  
+
+    Ok((city, filtered_df, imputed))  
+}  
+
+// The resolved city name with its daily readings, gaps included  
+fn load_city_daily_data(file_path: &str, city: &str) -> Result<(String, DataFrame), String> {  
+    let df = load_weather_data(file_path).map_err(|e| format!("Error loading weather data: {}", e))?;  
+
+    let city = location_match::resolve_location_in(&df, "Location", city)?;  
+
+    let daily_df = get_city_daily_data(&df, &city);  
+
+    Ok((city, daily_df))  
+}  
+
+// Gaps are filled on the daily readings so that they do not skew the period means, and  
+// only from the readings passed in, so a backtest passes its training days alone  
+fn aggregate_city_data(  
+    daily_df: &DataFrame,  
+    city: &str,  
+    granularity: Granularity,  
+    strategy: impute::Strategy,  
+) -> Result<(DataFrame, Vec<ImputationCount>), String> {  
+    let (imputed_df, imputed) = impute_missing(daily_df, strategy);  
+
+    let filtered_df = get_city_period_data(&imputed_df, granularity);  
+
+    if filtered_df.height() == 0 {  
+        return Err(format!("No data available for {}.", city));  
+    }  
+
+    Ok((filtered_df, imputed))  
+}  
+
+fn impute_missing(df: &DataFrame, strategy: impute::Strategy) -> (DataFrame, Vec<ImputationCount>) {  
+    let dates: Vec<NaiveDate> = df  
+        .column("Date")  
+        .unwrap()  
+        .date()  
+        .unwrap()  
+        .as_date_iter()  
+        .flatten()  
+        .collect();  
+
+    let mut imputed_df = df.clone();  
+    let mut counts = vec![];  
+
+    for field in df.schema().iter_fields() {  
+        let name = field.name();  
+        if !matches!(field.data_type(), DataType::Float64 | DataType::Int64 | DataType::Float32 | DataType::Int32) {  
+            continue;  
+        }  
+
+        let values: Vec<Option<f32>> = df  
+            .column(name)  
+            .unwrap()  
+            .cast(&DataType::Float32)  
+            .unwrap()  
+            .f32()  
+            .unwrap()  
+            .into_iter()  
+            .collect();  
+        let filled = impute::impute(&values, &dates, strategy);  
+
+        let missing = values.iter().filter(|v| v.is_none()).count();  
+        let remaining = filled.iter().filter(|v| v.is_none()).count();  
+        counts.push(ImputationCount {  
+            column: name.to_string(),  
+            missing: missing as u32,  
+            imputed: (missing - remaining) as u32,  
+        });  
+
+        let filled_series = Series::new(name, &filled);  
+        imputed_df.with_column(filled_series).unwrap();  
+    }  
+
+    (imputed_df, counts)  
+}  
+
+fn run_forecast(  
+    df: &DataFrame,  
+    city: &str,  
+    method: &ForecastMethod,  
+    horizon: usize,  
+    granularity: Granularity,  
+    confidence_level: f32,  
+    system_prompt_path: &str,  
+) -> Result<PredictionReport, String> {  
+    let season_length = granularity.season_length();  
+    let seasons = get_seasons(df, horizon, granularity);  
+    let fit = |column: &str, method: forecast::Method| {  
+        let series = get_period_series(df, column, granularity);  
+        forecast::forecast_seasons(&series, &seasons, horizon, season_length, method)  
+    };  
+
+    let mut llm_output_status = "".to_string();  
+    let mut llm_errors = vec![];  
+
+    // Each metric is fitted once and its standard errors give the interval; rainfall  
+    // keeps its own pass because that interval is built on the log scale  
+    let (min_temp, max_temp, wind_direction, wind_speed, rainfall, interval_method) = match method {  
+        ForecastMethod::Statistical(method) => {  
+            info!("Fitting {:?} forecast...", method);  
+            (  
+                fit("MinTemp", *method),  
+                fit("MaxTemp", *method),  
+                get_seasonal_wind_direction(df, horizon, granularity),  
+                fit("WindGustSpeed", *method),  
+                fit("Rainfall", *method).values.into_iter().map(|v| v.max(0.0)).collect(),  
+                *method,  
+            )  
+        }  
+        ForecastMethod::Llm => {  
+            let prompt = generate_prompt(df, city, horizon, granularity);  
+
+            let prediction = request_llm_prediction(&prompt, system_prompt_path, horizon);  
+            llm_output_status = prediction.status;  
+            llm_errors = prediction.errors;  
+
+            // The LLM gives no error estimate, so its intervals use the spread of seasonal-naive errors  
+            let with_naive_errors = |column: &str, values: Vec<f32>| forecast::Forecast {  
+                values,  
+                std_errors: fit(column, forecast::Method::SeasonalNaive).std_errors,  
+            };  
+            (  
+                with_naive_errors("MinTemp", prediction.min_temp),  
+                with_naive_errors("MaxTemp", prediction.max_temp),  
+                prediction.wind_direction,  
+                with_naive_errors("WindGustSpeed", prediction.wind_speed),  
+                prediction.rainfall,  
+                forecast::Method::SeasonalNaive,  
+            )  
+        }  
+    };  
+
+    // A defaulted LLM forecast is a row of zeros, and bounds around it would pass for a  
+    // real interval, so it gets none  
+    let defaulted = llm_output_status == "defaulted";  
+    let z = forecast::z_score(confidence_level);  
+    let interval = |point: &forecast::Forecast| {  
+        if defaulted {  
+            (vec![], vec![])  
+        } else {  
+            normal_interval(point, z)  
+        }  
+    };  
+    let (min_temp_lower, min_temp_upper) = interval(&min_temp);  
+    let (max_temp_lower, max_temp_upper) = interval(&max_temp);  
+    let (wind_speed_lower, wind_speed_upper) = interval(&wind_speed);  
+    let (rainfall_lower, rainfall_upper) = if defaulted {  
+        (vec![], vec![])  
+    } else {  
+        rainfall_interval(df, &rainfall, granularity, interval_method, confidence_level)  
+    };  
+
+    let (forecast_origin, periods) = match get_period_index(df, granularity).last() {  
+        Some(last) => {  
+            let mut target = *last;  
+            let periods = (0..horizon)  
+                .map(|_| {  
+                    target = granularity.next(target);  
+                    granularity.label(target)  
+                })  
+                .collect();  
+            (granularity.label(*last), periods)  
+        }  
+        None => ("".to_string(), vec!["".to_string(); horizon]),  
+    };  
+
+    Ok(PredictionReport {  
+        error: "".to_string(),  
+        forecast_origin,  
+        periods,  
+        min_temp: min_temp.values,  
+        max_temp: max_temp.values,  
+        wind_direction,  
+        wind_speed: wind_speed.values,  
+        rainfall,  
+        confidence_level,  
+        min_temp_lower,  
+        min_temp_upper,  
+        max_temp_lower,  
+        max_temp_upper,  
+        wind_speed_lower: wind_speed_lower.into_iter().map(|v| v.max(0.0)).collect(),  
+        wind_speed_upper,  
+        rainfall_lower,  
+        rainfall_upper,  
+        llm_output_status,  
+        llm_errors,  
+        imputation: "".to_string(),  
+        imputed: vec![],  
+    })  
+}  
+
+// Accepts 0.8 or 80 alike; 0 selects the default. Values from 1 to 50 are rejected  
+// because they are ambiguous: 1 could mean 100% as easily as 1%  
+fn parse_confidence_level(confidence_level: f32) -> Option<f32> {  
+    if confidence_level == 0.0 {  
+        Some(DEFAULT_CONFIDENCE_LEVEL)  
+    } else if confidence_level > 0.0 && confidence_level < 1.0 {  
+        Some(confidence_level)  
+    } else if confidence_level > 50.0 && confidence_level < 100.0 {  
+        Some(confidence_level / 100.0)  
+    } else {  
+        None  
+    }  
+}  
+
+fn normal_interval(point: &forecast::Forecast, z: f32) -> (Vec<f32>, Vec<f32>) {  
+    point  
+        .values  
+        .iter()  
+        .zip(&point.std_errors)  
+        .map(|(p, se)| (p - z * se, p + z * se))  
+        .unzip()  
+}  
+
+// Rainfall is skewed and often zero, so the interval is built on the log1p scale  
+// and the lower bound drops to zero when dry periods are common enough  
+fn rainfall_interval(  
+    df: &DataFrame,  
+    point: &[f32],  
+    granularity: Granularity,  
+    method: forecast::Method,  
+    confidence_level: f32,  
+) -> (Vec<f32>, Vec<f32>) {  
+    let z = forecast::z_score(confidence_level);  
+    let series = get_period_series(df, "Rainfall", granularity);  
+    let log_series: Vec<f32> = series.iter().map(|v| v.max(0.0).ln_1p()).collect();  
+    let seasons = get_seasons(df, point.len(), granularity);  
+    let std_errors =  
+        forecast::forecast_seasons(&log_series, &seasons, point.len(), granularity.season_length(), method).std_errors;  
+
+    let zero_share = if series.is_empty() {  
+        0.0  
+    } else {  
+        series.iter().filter(|v| **v <= 0.0).count() as f32 / series.len() as f32  
+    };  
+    let lower_tail = (1.0 - confidence_level) / 2.0;  
+
+    point  
+        .iter()  
+        .zip(std_errors)  
+        .map(|(p, se)| {  
+            let centre = p.max(0.0).ln_1p();  
+            let lower = if zero_share >= lower_tail {  
+                0.0  
+            } else {  
+                (centre - z * se).exp_m1().max(0.0)  
+            };  
+            (lower, (centre + z * se).exp_m1())  
+        })  
+        .unzip()  
+}  
+
+// The per-period aggregates up to and including the `cutoff` period, imputed from the days  
+// in those periods only  
+fn get_training_data(  
+    daily_df: &DataFrame,  
+    city: &str,  
+    cutoff: NaiveDate,  
+    granularity: Granularity,  
+    strategy: impute::Strategy,  
+) -> Result<DataFrame, String> {  
+    let end = granularity.next(cutoff);  
+    let mask: BooleanChunked = daily_df  
+        .column("Date")  
+        .and_then(|dates| dates.date().cloned())  
+        .map_err(|e| e.to_string())?  
+        .as_date_iter()  
+        .map(|date| date.is_some_and(|date| date < end))  
+        .collect();  
+    let training_days = daily_df.filter(&mask).map_err(|e| e.to_string())?;  
+
+    aggregate_city_data(&training_days, city, granularity, strategy).map(|(training_df, _)| training_df)  
+}  
+
+fn forecast_accuracy(metric: &str, predicted: &[f32], actual: &[f32]) -> ForecastAccuracy {  
+    let pairs: Vec<(f32, f32)> = predicted.iter().copied().zip(actual.iter().copied()).collect();  
+    let n = pairs.len().max(1) as f32;  
+
+    let errors: Vec<f32> = pairs.iter().map(|(p, a)| p - a).collect();  
+    let mae = errors.iter().map(|e| e.abs()).sum::<f32>() / n;  
+    let rmse = (errors.iter().map(|e| e * e).sum::<f32>() / n).sqrt();  
+    let bias = errors.iter().sum::<f32>() / n;  
+
+    // Periods with zero actuals (common for rainfall) are left out of MAPE  
+    let percentage_errors: Vec<f32> = pairs  
+        .iter()  
+        .filter(|(_, a)| *a != 0.0)  
+        .map(|(p, a)| ((p - a) / a).abs() * 100.0)  
+        .collect();  
+    let mape = if percentage_errors.is_empty() {  
+        None  
+    } else {  
+        Some(percentage_errors.iter().sum::<f32>() / percentage_errors.len() as f32)  
+    };  
+
+    ForecastAccuracy {  
+        metric: metric.to_string(),  
+        mae,  
+        rmse,  
+        mape,  
+        bias,  
+        observations: pairs.len() as u32,  
     }  
 }  
 
 fn empty_prediction_report() -> PredictionReport {  
     PredictionReport {  
-        min_temp: vec![0.0; 12],  
-        wind_direction: vec!["".to_string(); 12],  
-        wind_speed: vec![0.0; 12],  
-        rainfall: vec![0.0; 12],  
+        error: "".to_string(),  
+        forecast_origin: "".to_string(),  
+        periods: vec![],  
+        min_temp: vec![],  
+        max_temp: vec![],  
+        wind_direction: vec![],  
+        wind_speed: vec![],  
+        rainfall: vec![],  
+        confidence_level: 0.0,  
+        min_temp_lower: vec![],  
+        min_temp_upper: vec![],  
+        max_temp_lower: vec![],  
+        max_temp_upper: vec![],  
+        wind_speed_lower: vec![],  
+        wind_speed_upper: vec![],  
+        rainfall_lower: vec![],  
+        rainfall_upper: vec![],  
+        llm_output_status: "".to_string(),  
+        llm_errors: vec![],  
+        imputation: "".to_string(),  
+        imputed: vec![],  
     }  
 }  
 
+fn get_periods(df: &DataFrame, granularity: Granularity) -> Vec<Option<NaiveDate>> {  
+    df.column(granularity.column_name())  
+        .unwrap()  
+        .utf8()  
+        .unwrap()  
+        .into_iter()  
+        .map(|opt_s| opt_s.and_then(|s| granularity.parse_label(s)))  
+        .collect()  
+}  
+
+// The aggregated frame comes out of the groupby unordered and may skip periods;  
+// this returns a contiguous, chronologically ordered series with gaps carried forward  
+fn get_period_series(df: &DataFrame, column: &str, granularity: Granularity) -> Vec<f32> {  
+    let values = get_period_values(df, column, granularity);  
+
+    let mut previous = match values.iter().flatten().next() {  
+        Some(first) => *first,  
+        None => return vec![],  
+    };  
+
+    values  
+        .into_iter()  
+        .map(|value| {  
+            let value = value.unwrap_or(previous);  
+            previous = value;  
+            value  
+        })  
+        .collect()  
+}  
+
+// One value per period of get_period_index, in order, with None where a period has no value  
+fn get_period_values(df: &DataFrame, column: &str, granularity: Granularity) -> Vec<Option<f32>> {  
+    let values: Vec<Option<f32>> = df  
+        .column(column)  
+        .ok()  
+        .and_then(|s| s.cast(&DataType::Float32).ok())  
+        .map(|s| s.f32().unwrap().into_iter().collect())  
+        .unwrap_or_default();  
+
+    let by_period: BTreeMap<NaiveDate, f32> = get_periods(df, granularity)  
+        .into_iter()  
+        .zip(values)  
+        .filter_map(|(period, value)| Some((period?, value?)))  
+        .collect();  
+
+    get_period_index(df, granularity)  
+        .into_iter()  
+        .map(|period| by_period.get(&period).copied())  
+        .collect()  
+}  
+
+// Calendar season of every period in get_period_index, then of the `horizon` periods after it  
+fn get_seasons(df: &DataFrame, horizon: usize, granularity: Granularity) -> Vec<usize> {  
+    let mut periods = get_period_index(df, granularity);  
+    if let Some(last) = periods.last().copied() {  
+        let mut target = last;  
+        for _ in 0..horizon {  
+            target = granularity.next(target);  
+            periods.push(target);  
+        }  
+    }  
+
+    periods.into_iter().map(|period| granularity.season_index(period)).collect()  
+}  
+
+// Every period from the first to the last observed one  
+fn get_period_index(df: &DataFrame, granularity: Granularity) -> Vec<NaiveDate> {  
+    let periods = get_periods(df, granularity);  
+    let (first, last) = match (periods.iter().flatten().min(), periods.iter().flatten().max()) {  
+        (Some(first), Some(last)) => (*first, *last),  
+        _ => return vec![],  
+    };  
+
+    let mut index = vec![];  
+    let mut current = first;  
+    while current <= last {  
+        index.push(current);  
+        current = granularity.next(current);  
+    }  
+
+    index  
+}  
+
+// Wind direction is categorical, so it is forecast as the most common direction  
+// for the same point in the season across past years  
+fn get_seasonal_wind_direction(df: &DataFrame, horizon: usize, granularity: Granularity) -> Vec<String> {  
+    let directions: Vec<Option<String>> = match df.column("WindGustDir") {  
+        Ok(s) => match s.dtype() {  
+            DataType::List(_) => s  
+                .list()  
+                .unwrap()  
+                .into_iter()  
+                .map(|opt_s| opt_s.and_then(|s| s.utf8().ok().and_then(|ca| ca.get(0).map(|d| d.to_string()))))  
+                .collect(),  
+            _ => s  
+                .utf8()  
+                .map(|ca| ca.into_iter().map(|opt_s| opt_s.map(|d| d.to_string())).collect())  
+                .unwrap_or_default(),  
+        },  
+        Err(_) => vec![],  
+    };  
+
+    let periods = get_periods(df, granularity);  
+    let mut counts: BTreeMap<(usize, String), u32> = BTreeMap::new();  
+    for (period, direction) in periods.iter().zip(directions) {  
+        if let (Some(period), Some(direction)) = (period, direction) {  
+            if !direction.is_empty() {  
+                *counts.entry((granularity.season_index(*period), direction)).or_insert(0) += 1;  
+            }  
+        }  
+    }  
+
+    let last = periods.iter().flatten().max().copied();  
+    let mut target = match last {  
+        Some(last) => granularity.next(last),  
+        None => return vec!["".to_string(); horizon],  
+    };  
+
+    (0..horizon)  
+        .map(|_| {  
+            let position = granularity.season_index(target);  
+            target = granularity.next(target);  
+            counts  
+                .iter()  
+                .filter(|((p, _), _)| *p == position)  
+                .max_by_key(|(_, count)| **count)  
+                .map(|((_, direction), _)| direction.clone())  
+                .unwrap_or_default()  
+        })  
+        .collect()  
+}  
+
 fn load_weather_data<P: AsRef<Path>>(file_path: P) -> Result<DataFrame, Box<dyn Error>> {  
-    let df = CsvReader::from_path(file_path)?  
+    let df = CsvReader::from_path(file_path.as_ref())?  
         .infer_schema(None)  
         .has_header(true)  
         .finish()?;  
     Ok(df)  
 }  
 
-fn get_city_month_data(df: &DataFrame, city: &str) -> DataFrame {  
+// The city's rows with a parsed Date column; rows without a valid date are dropped  
+fn get_city_daily_data(df: &DataFrame, city: &str) -> DataFrame {  
     // Filter data for the specified city  
     let city_filter = df  
         .column("Location")  
         .unwrap()  
         .utf8()  
         .unwrap()  
-        .equal(city)  
-        .unwrap();  
+        .equal(city);  
 
     let mut filtered_df = df.filter(&city_filter).unwrap();  
 
@@ -120,119 +1324,265 @@ fn get_city_month_data(df: &DataFrame, city: &str) -> DataFrame {
         .collect();  
 
     let date_series = Series::new("Date", &dates);  
-    filtered_df.replace_or_add(&date_series).unwrap();  
+    filtered_df.with_column(date_series).unwrap();  
 
-    // Drop rows with invalid or missing dates  
-    filtered_df = filtered_df  
-        .drop_nulls(None)  
-        .unwrap_or_else(|_| DataFrame::default());  
+    // Drop rows with invalid or missing dates; other gaps are left to the imputation  
+    filtered_df  
+        .drop_nulls(Some(&["Date".to_string()]))  
+        .unwrap_or_else(|_| DataFrame::default())  
+}  
 
-    // Add 'Year-Month' column  
-    let year_months: Vec<String> = dates  
-        .into_iter()  
+fn get_city_period_data(df: &DataFrame, granularity: Granularity) -> DataFrame {  
+    let mut filtered_df = df.clone();  
+
+    // Add the period column ('Year-Month' for monthly data) from the parsed dates  
+    let periods: Vec<String> = filtered_df  
+        .column("Date")  
+        .unwrap()  
+        .date()  
+        .unwrap()  
+        .as_date_iter()  
         .map(|opt_date| {  
             opt_date  
-                .map(|date| format!("{}-{:02}", date.year(), date.month()))  
+                .map(|date| granularity.label(granularity.period_start(date)))  
                 .unwrap_or_else(|| "".to_string())  
         })  
         .collect();  
 
-    let year_month_series = Series::new("Year-Month", &year_months);  
-    filtered_df  
-        .replace_or_add(&year_month_series)  
-        .unwrap();  
+    let period_series = Series::new(granularity.column_name(), &periods);  
+    filtered_df.with_column(period_series).unwrap();  
 
     // Define aggregation rules  
-    let mut groups = vec!["Year-Month"];  
+    let groups = vec![granularity.column_name()];  
     let mut agg_exprs = vec![];  
 
-    for field in filtered_df.get_schema().iter_fields() {  
+    for field in filtered_df.schema().iter_fields() {  
         let name = field.name();  
-        if name == "Location" || name == "Date" || name == "Year-Month" {  
+        if name == "Location" || name == "Date" || name == granularity.column_name() {  
             continue;  
         } else if matches!(field.data_type(), DataType::Float64 | DataType::Int64 | DataType::Float32 | DataType::Int32) {  
             agg_exprs.push(col(name).mean().alias(name));  
         } else {  
-            agg_exprs.push(col(name).mode().alias(name));  
+            // The mode of a group is a list (ties included); taking its first value keeps  
+            // the column flat so that the frame can still be written out as CSV for the prompt  
+            agg_exprs.push(col(name).mode().first().alias(name));  
         }  
     }  
 
-    // Group by 'Year-Month' and aggregate  
-    let aggregated_df = filtered_df  
+    // Group by period and aggregate  
+    filtered_df  
         .lazy()  
         .groupby(groups)  
         .agg(agg_exprs)  
         .collect()  
-        .unwrap_or_else(|_| DataFrame::default());  
-
-    aggregated_df  
+        .unwrap_or_else(|_| DataFrame::default())  
 }  
 
-fn generate_prompt(df: &DataFrame, city: &str) -> String {  
+fn generate_prompt(df: &DataFrame, city: &str, horizon: usize, granularity: Granularity) -> String {  
     if df.height() == 0 {  
         return format!("No data available for {}.", city);  
     }  
 
     let mut buffer = Vec::new();  
-    let mut csv_writer = CsvWriter::new(&mut buffer);  
+    let csv_writer = CsvWriter::new(&mut buffer);  
 
     csv_writer  
-        .has_headers(true)  
+        .has_header(true)  
         .with_delimiter(b',')  
-        .finish(df)  
+        .finish(&mut df.clone())  
         .unwrap();  
 
     let csv_data = String::from_utf8(buffer).unwrap_or_default();  
 
     format!(  
-        "Below is the weather data for {}:\n\n{}",  
-        city, csv_data  
+        "Below is the weather data for {}:\n\n{}\nPredict the next {} {}s. \  
+        Respond with only a JSON object matching this schema:\n{}",  
+        city,  
+        csv_data,  
+        horizon,  
+        granularity.name(),  
+        prediction_schema(horizon)  
     )  
 }  
 
-fn parse_input(report: &str) -> (Vec<f32>, Vec<String>, Vec<f32>, Vec<f32>) {  
-    if report.is_empty() {  
-        let zero_floats = vec![0.0; 12];  
-        let empty_strings = vec!["".to_string(); 12];  
-        return (zero_floats.clone(), empty_strings, zero_floats.clone(), zero_floats);  
-    }  
+struct LlmPrediction {  
+    min_temp: Vec<f32>,  
+    max_temp: Vec<f32>,  
+    wind_direction: Vec<String>,  
+    wind_speed: Vec<f32>,  
+    rainfall: Vec<f32>,  
+    // "parsed", "repaired" or "defaulted"  
+    status: String,  
+    errors: Vec<String>,  
+}  
 
-    let lines = report.lines();  
-    let mut data = std::collections::HashMap::new();  
+fn prediction_schema(horizon: usize) -> String {  
+    let number_array = serde_json::json!({  
+        "type": "array",  
+        "items": {"type": "number"},  
+        "minItems": horizon,  
+        "maxItems": horizon  
+    });  
+    let non_negative_array = serde_json::json!({  
+        "type": "array",  
+        "items": {"type": "number", "minimum": 0},  
+        "minItems": horizon,  
+        "maxItems": horizon  
+    });  
 
-    for line in lines {  
-        if let Some((key, value)) = line.split_once(":") {  
-            data.insert(key.trim().to_string(), value.trim().to_string());  
+    serde_json::json!({  
+        "type": "object",  
+        "required": ["min_temp", "max_temp", "wind_direction", "wind_speed", "rainfall"],  
+        "properties": {  
+            "min_temp": number_array,  
+            "max_temp": number_array,  
+            "wind_direction": {  
+                "type": "array",  
+                "items": {"type": "string", "enum": COMPASS_POINTS},  
+                "minItems": horizon,  
+                "maxItems": horizon  
+            },  
+            "wind_speed": non_negative_array,  
+            "rainfall": non_negative_array  
+        }  
+    })  
+    .to_string()  
+}  
+
+// Sends the prompt, then feeds schema violations back to the model until the  
+// reply validates or the repair attempts run out  
+fn request_llm_prediction(prompt: &str, system_prompt_path: &str, horizon: usize) -> LlmPrediction {  
+    let defaulted = |errors: Vec<String>| LlmPrediction {  
+        min_temp: vec![0.0; horizon],  
+        max_temp: vec![0.0; horizon],  
+        wind_direction: vec!["".to_string(); horizon],  
+        wind_speed: vec![0.0; horizon],  
+        rainfall: vec![0.0; horizon],  
+        status: "defaulted".to_string(),  
+        errors,  
+    };  
+
+    let mut messages = vec![  
+        serde_json::json!({"role": "system", "content": load_system_prompt(system_prompt_path)}),  
+        serde_json::json!({"role": "user", "content": prompt}),  
+    ];  
+    let mut errors = vec![];  
+
+    for attempt in 0..=MAX_REPAIR_ATTEMPTS {  
+        info!("Calling API (attempt {})...", attempt + 1);  
+        let reply = match call_api(&messages) {  
+            Ok(reply) => reply,  
+            Err(e) => {  
+                errors.push(format!("Error in API call: {}", e));  
+                return defaulted(errors);  
+            }  
+        };  
+
+        match validate_prediction(&reply, horizon) {  
+            Ok(prediction) => {  
+                return LlmPrediction {  
+                    status: if attempt == 0 { "parsed" } else { "repaired" }.to_string(),  
+                    errors,  
+                    ..prediction  
+                }  
+            }  
+            Err(validation_errors) => {  
+                info!("Invalid prediction: {}", validation_errors.join("; "));  
+                let repair_prompt = format!(  
+                    "Your reply did not match the schema:\n- {}\nReply again with only the corrected JSON object.",  
+                    validation_errors.join("\n- ")  
+                );  
+                errors.extend(validation_errors);  
+                messages.push(serde_json::json!({"role": "assistant", "content": reply}));  
+                messages.push(serde_json::json!({"role": "user", "content": repair_prompt}));  
+            }  
         }  
     }  
 
-    let parse_floats = |s: &str| -> Vec<f32> {  
-        s.split(',')  
-            .filter_map(|v| v.trim().parse::<f32>().ok())  
-            .collect()  
+    defaulted(errors)  
+}  
+
+fn validate_prediction(reply: &str, horizon: usize) -> Result<LlmPrediction, Vec<String>> {  
+    // Models often wrap the object in prose or code fences  
+    let json_text = match (reply.find('{'), reply.rfind('}')) {  
+        (Some(start), Some(end)) if start < end => &reply[start..=end],  
+        _ => return Err(vec!["Reply does not contain a JSON object".to_string()]),  
     };  
 
-    let min_temp = data  
-        .get("MinTemp")  
-        .map(|s| parse_floats(s))  
-        .unwrap_or_else(|| vec![0.0; 12]);  
+    let value: serde_json::Value = match serde_json::from_str(json_text) {  
+        Ok(value) => value,  
+        Err(e) => return Err(vec![format!("Reply is not valid JSON: {}", e)]),  
+    };  
 
-    let wind_direction = data  
-        .get("WindDirection")  
-        .map(|s| s.split(',').map(|v| v.trim().to_string()).collect())  
-        .unwrap_or_else(|| vec!["".to_string(); 12]);  
+    let mut errors = vec![];  
 
-    let wind_speed = data  
-        .get("WindSpeed")  
-        .map(|s| parse_floats(s))  
-        .unwrap_or_else(|| vec![0.0; 12]);  
+    let mut array = |field: &str| -> Vec<serde_json::Value> {  
+        match value.get(field).and_then(|v| v.as_array()) {  
+            Some(items) if items.len() == horizon => items.clone(),  
+            Some(items) => {  
+                errors.push(format!("\"{}\" has {} items, expected {}", field, items.len(), horizon));  
+                vec![]  
+            }  
+            None => {  
+                errors.push(format!("\"{}\" is missing or not an array", field));  
+                vec![]  
+            }  
+        }  
+    };  
 
-    let rainfall = data  
-        .get("Rainfall")  
-        .map(|s| parse_floats(s))  
-        .unwrap_or_else(|| vec![0.0; 12]);  
+    let min_temp_items = array("min_temp");  
+    let max_temp_items = array("max_temp");  
+    let wind_direction_items = array("wind_direction");  
+    let wind_speed_items = array("wind_speed");  
+    let rainfall_items = array("rainfall");  
 
-    (min_temp, wind_direction, wind_speed, rainfall)  
+    let numbers = |field: &str, items: &[serde_json::Value], non_negative: bool, errors: &mut Vec<String>| -> Vec<f32> {  
+        items  
+            .iter()  
+            .enumerate()  
+            .map(|(i, item)| match item.as_f64() {  
+                Some(v) if non_negative && v < 0.0 => {  
+                    errors.push(format!("\"{}\"[{}] must not be negative", field, i));  
+                    0.0  
+                }  
+                Some(v) => v as f32,  
+                None => {  
+                    errors.push(format!("\"{}\"[{}] is not a number", field, i));  
+                    0.0  
+                }  
+            })  
+            .collect()  
+    };  
+
+    let min_temp = numbers("min_temp", &min_temp_items, false, &mut errors);  
+    let max_temp = numbers("max_temp", &max_temp_items, false, &mut errors);  
+    let wind_speed = numbers("wind_speed", &wind_speed_items, true, &mut errors);  
+    let rainfall = numbers("rainfall", &rainfall_items, true, &mut errors);  
+    let wind_direction = wind_direction_items  
+        .iter()  
+        .enumerate()  
+        .map(|(i, item)| match item.as_str() {  
+            Some(d) if COMPASS_POINTS.contains(&d) => d.to_string(),  
+            _ => {  
+                errors.push(format!("\"wind_direction\"[{}] must be one of {}", i, COMPASS_POINTS.join(", ")));  
+                "".to_string()  
+            }  
+        })  
+        .collect();  
+
+    if errors.is_empty() {  
+        Ok(LlmPrediction {  
+            min_temp,  
+            max_temp,  
+            wind_direction,  
+            wind_speed,  
+            rainfall,  
+            status: "parsed".to_string(),  
+            errors: vec![],  
+        })  
+    } else {  
+        Err(errors)  
+    }  
 }  
 
 fn load_system_prompt<P: AsRef<Path>>(path: P) -> String {  
@@ -243,7 +1593,11 @@ fn get_env_api_key() -> String {
     env::var("API_KEY").unwrap_or_else(|_| "".to_string())  
 }  
 
-fn call_api(prompt: &str, system_prompt_path: &str) -> Result<String, Box<dyn Error>> {  
+fn get_env_api_url() -> String {  
+    env::var("LLM_API_URL").unwrap_or_else(|_| DEFAULT_LLM_API_URL.to_string())  
+}  
+
+fn call_api(messages: &[serde_json::Value]) -> Result<String, Box<dyn Error>> {  
     let rt = Runtime::new()?;  
 
     let response = rt.block_on(async {  
@@ -254,29 +1608,106 @@ fn call_api(prompt: &str, system_prompt_path: &str) -> Result<String, Box<dyn Er
             return Err("API key not provided".into());  
         }  
 
-        let system_prompt = load_system_prompt(system_prompt_path);  
-
         let request_body = serde_json::json!({  
             "model": "llama3-8b-8192",  
-            "messages": [  
-                {"role": "system", "content": system_prompt},  
-                {"role": "user", "content": prompt}  
-            ]  
+            "messages": messages  
         });  
 
         let res = client  
-            .post("https://api.example.com/v1/chat/completions") // Replace with actual API endpoint  
+            .post(get_env_api_url())  
             .bearer_auth(api_key)  
             .json(&request_body)  
             .send()  
             .await?;  
 
+        if !res.status().is_success() {  
+            return Err(format!("Error response from API: {}", res.status()).into());  
+        }  
+
         let res_json: serde_json::Value = res.json().await?;  
-        Ok(res_json["choices"][0]["message"]["content"]  
+        Ok::<String, Box<dyn Error>>(res_json["choices"][0]["message"]["content"]  
             .as_str()  
             .unwrap_or("")  
             .to_string())  
     })?;  
 
     Ok(response)  
+}  
+
+#[cfg(test)]  
+mod tests {  
+    use super::*;  
+
+    fn date(s: &str) -> NaiveDate {  
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()  
+    }  
+
+    #[test]  
+    fn week_53_shares_the_season_of_week_52() {  
+        let weekly = Granularity::Weekly;  
+        // 2015 has 53 ISO weeks, so its week 53 starts on 28 December  
+        assert_eq!(weekly.season_index(date("2015-12-21")), 51);  
+        assert_eq!(weekly.season_index(date("2015-12-28")), 51);  
+        assert_eq!(weekly.season_index(date("2016-01-04")), 0);  
+        // 105 weeks apart is not a multiple of 52, but both dates start the second ISO week  
+        let start = date("2015-01-05");  
+        assert_eq!(weekly.season_index(start), weekly.season_index(start + Duration::weeks(105)));  
+        assert!((0..400).all(|i| weekly.season_index(date("2015-01-05") + Duration::weeks(i)) < 52));  
+    }  
+
+    #[test]  
+    fn holdout_days_cannot_change_the_training_periods() {  
+        let start = date("2015-01-01");  
+        let days: Vec<NaiveDate> = (0..456).map(|i| start + Duration::days(i)).collect();  
+        let cutoff = date("2016-01-01");  
+        // Every fourth day is missing, as is the last training day  
+        let daily = |holdout_scale: f32| {  
+            let rainfall: Vec<Option<f32>> = days  
+                .iter()  
+                .enumerate()  
+                .map(|(i, day)| match (i % 4, *day) {  
+                    (0, _) => None,  
+                    (_, day) if day == date("2016-01-31") => None,  
+                    (_, day) if day > date("2016-01-31") => Some(holdout_scale * (i % 7) as f32),  
+                    _ => Some((i % 7) as f32),  
+                })  
+                .collect();  
+            df!("Date" => &days, "Rainfall" => &rainfall).unwrap()  
+        };  
+
+        let strategies = [impute::Strategy::Linear, impute::Strategy::SeasonalMean, impute::Strategy::ForwardFill];  
+        for strategy in strategies.iter() {  
+            let training = |holdout_scale: f32| {  
+                let training_df = get_training_data(&daily(holdout_scale), "Sydney", cutoff, Granularity::Monthly, *strategy)  
+                    .unwrap();  
+                get_period_series(&training_df, "Rainfall", Granularity::Monthly)  
+            };  
+            let training_series = training(1.0);  
+            assert_eq!(training_series.len(), 13);  
+            assert_eq!(training_series, training(100.0), "{:?}", strategy);  
+        }  
+    }  
+
+    #[test]  
+    fn missing_periods_are_carried_forward_only_in_the_period_series() {  
+        let df = df!(  
+            "Year-Month" => &["2016-03", "2016-01", "2016-04"],  
+            "Rainfall" => &[Some(3.0f32), Some(1.0), None]  
+        )  
+        .unwrap();  
+
+        let values = get_period_values(&df, "Rainfall", Granularity::Monthly);  
+        assert_eq!(values, vec![Some(1.0), None, Some(3.0), None]);  
+        let series = get_period_series(&df, "Rainfall", Granularity::Monthly);  
+        assert_eq!(series, vec![1.0, 1.0, 3.0, 3.0]);  
+    }  
+
+    #[test]  
+    fn leap_day_shares_the_season_of_28_february() {  
+        let daily = Granularity::Daily;  
+        assert_eq!(daily.season_index(date("2016-02-29")), daily.season_index(date("2016-02-28")));  
+        assert_eq!(daily.season_index(date("2016-03-01")), daily.season_index(date("2015-03-01")));  
+        assert_eq!(daily.season_index(date("2016-12-31")), 364);  
+        assert_eq!(Granularity::Monthly.season_index(date("2016-12-01")), 11);  
+    }  
 }
\ No newline at end of file
@@ -0,0 +1,153 @@
+// tests/llm_forecast.rs  
+
+// Runs the LLM forecast against the mock server's recorded chat completions  
+
+#[path = "../src/main.rs"]  
+#[allow(dead_code)]  
+mod pipeline2;  
+
+use std::env;  
+use std::fs;  
+use std::process;  
+use std::sync::{Mutex, MutexGuard};  
+
+use chrono::{Datelike, Duration, NaiveDate};  
+use mock_server::fixtures;  
+use mock_server::{MockServer, Route};  
+use serde_json::Value;  
+
+use pipeline2::generate_prediction_weather_report;  
+
+static ENV_LOCK: Mutex<()> = Mutex::new(());  
+
+fn stub(scenario: &str) -> (MutexGuard<'static, ()>, MockServer) {  
+    let guard = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());  
+    let routes: Vec<Route> = fixtures::scenario(scenario).unwrap();  
+    let server = MockServer::start(routes).unwrap();  
+
+    write_dataset();  
+    env::set_var("API_KEY", "test-key");  
+    env::set_var("LLM_API_URL", format!("{}{}", server.url(), fixtures::CHAT_COMPLETIONS_PATH));  
+
+    (guard, server)  
+}  
+
+// Two years of daily readings for one location, enough for a monthly forecast  
+fn write_dataset() {  
+    let dir = env::temp_dir().join(format!("pipeline2-tests-{}", process::id()));  
+    fs::create_dir_all(&dir).unwrap();  
+
+    let mut csv = "Date,Location,MinTemp,MaxTemp,Rainfall,WindGustDir,WindGustSpeed\n".to_string();  
+    let start = NaiveDate::from_ymd_opt(2015, 1, 1).unwrap();  
+    for i in 0..730 {  
+        let date = start + Duration::days(i as i64);  
+        let season = (date.ordinal() as f32 / 365.0 * 2.0 * std::f32::consts::PI).cos();  
+        csv.push_str(&format!(  
+            "{},Sydney,{:.1},{:.1},{:.1},{},{:.1}\n",  
+            date.format("%Y-%m-%d"),  
+            14.0 + 5.0 * season,  
+            22.0 + 5.0 * season,  
+            (i % 5) as f32 * 1.5,  
+            ["NE", "S", "W"][i % 3],  
+            35.0 + (i % 7) as f32  
+        ));  
+    }  
+
+    fs::write(dir.join("weatherAUS.csv"), csv).unwrap();  
+    env::set_current_dir(&dir).unwrap();  
+}  
+
+fn llm_forecast(city: &str) -> Value {  
+    let report = generate_prediction_weather_report(  
+        city.to_string(),  
+        "llm".to_string(),  
+        0.0,  
+        0,  
+        "monthly".to_string(),  
+        "".to_string(),  
+    );  
+    serde_json::to_value(&report).unwrap()  
+}  
+
+fn len(value: &Value) -> usize {  
+    value.as_array().map_or(0, |items| items.len())  
+}  
+
+// A failed forecast has no series, whichever step failed  
+fn assert_failure_is_empty(report: &Value) {  
+    for field in ["periods", "min_temp", "wind_direction", "rainfall", "min_temp_lower", "rainfall_upper"].iter() {  
+        assert_eq!(len(&report[*field]), 0, "{}: {}", field, report[*field]);  
+    }  
+}  
+
+#[test]  
+fn valid_reply_is_parsed() {  
+    let (_guard, server) = stub("ok");  
+
+    let report = llm_forecast("Sydney");  
+    assert_eq!(report["error"], "");  
+    assert_eq!(report["llm_output_status"], "parsed");  
+    assert_eq!(len(&report["llm_errors"]), 0);  
+    assert_eq!(len(&report["min_temp"]), 12);  
+    assert_eq!(report["forecast_origin"], "2016-12");  
+    assert_eq!(report["periods"][0], "2017-01");  
+    assert_eq!(len(&report["min_temp_lower"]), 12);  
+    assert_eq!(len(&report["rainfall_upper"]), 12);  
+
+    let requests = server.requests();  
+    assert_eq!(requests.len(), 1);  
+    assert!(requests[0].starts_with("POST /v1/chat/completions"), "{}", requests[0]);  
+}  
+
+#[test]  
+fn invalid_reply_is_repaired_by_the_follow_up_prompt() {  
+    let (_guard, server) = stub("llm_invalid_then_valid");  
+
+    let report = llm_forecast("Sydney");  
+    assert_eq!(report["error"], "");  
+    assert_eq!(report["llm_output_status"], "repaired");  
+    assert!(len(&report["llm_errors"]) > 0);  
+    assert_eq!(len(&report["min_temp_lower"]), 12);  
+    assert_eq!(server.requests().len(), 2);  
+}  
+
+#[test]  
+fn reply_that_never_validates_is_reported_as_a_failure() {  
+    for scenario in ["llm_invalid", "llm_missing_content"].iter() {  
+        let (_guard, server) = stub(scenario);  
+
+        let report = llm_forecast("Sydney");  
+        assert_eq!(report["llm_output_status"], "defaulted", "{}", scenario);  
+        assert_ne!(report["error"], "", "{}", scenario);  
+        assert_failure_is_empty(&report);  
+        // The first reply and every repair attempt  
+        assert_eq!(server.requests().len(), 3, "{}", scenario);  
+    }  
+}  
+
+#[test]  
+fn failed_call_is_defaulted_without_repair_attempts() {  
+    for scenario in ["llm_rate_limited", "malformed_json"].iter() {  
+        let (_guard, server) = stub(scenario);  
+
+        let report = llm_forecast("Sydney");  
+        assert_eq!(report["llm_output_status"], "defaulted", "{}", scenario);  
+        assert_ne!(report["error"], "", "{}", scenario);  
+        assert_failure_is_empty(&report);  
+        assert_eq!(server.requests().len(), 1, "{}", scenario);  
+    }  
+
+    let (_guard, _server) = stub("llm_rate_limited");  
+    let report = llm_forecast("Sydney");  
+    assert!(report["llm_errors"][0].as_str().unwrap().contains("429"), "{}", report["llm_errors"]);  
+}  
+
+#[test]  
+fn unknown_city_is_reported_with_suggestions() {  
+    let (_guard, server) = stub("ok");  
+
+    let report = llm_forecast("Sydny");  
+    assert_eq!(report["error"], "Unknown location: Sydny. Did you mean: Sydney?");  
+    assert_failure_is_empty(&report);  
+    assert!(server.requests().is_empty());  
+}
\ No newline at end of file
@@ -0,0 +1,192 @@
+// src/cache.rs  
+
+// File-backed response cache on the module's mounted filesystem, one JSON file per key  
+
+use serde_json::Value;  
+use std::fs;  
+use std::io;  
+use std::path::{Path, PathBuf};  
+use std::sync::atomic::{AtomicBool, Ordering};  
+use std::time::{SystemTime, UNIX_EPOCH};  
+
+// Set once a failed write has been reported, so a missing or read-only volume logs once, not per call  
+static WRITE_FAILURE_REPORTED: AtomicBool = AtomicBool::new(false);  
+
+pub struct Cached {  
+    pub body: Value,  
+    pub age_seconds: u64,  
+}  
+
+// Returns the stored response for `key` if it is younger than `ttl_seconds`  
+pub fn read(dir: &str, key: &str, ttl_seconds: u64) -> Option<Cached> {  
+    let entry: Value = serde_json::from_str(&fs::read_to_string(entry_path(dir, key)).ok()?).ok()?;  
+
+    // File names are hashes, so the stored key guards against collisions  
+    if entry["key"].as_str() != Some(key) {  
+        return None;  
+    }  
+
+    let age_seconds = age(&entry)?;  
+    if age_seconds >= ttl_seconds {  
+        return None;  
+    }  
+
+    Some(Cached {  
+        body: entry["body"].clone(),  
+        age_seconds,  
+    })  
+}  
+
+// Failing to write only costs a later cache miss, so the response is still returned and the  
+// first failure is logged to stderr. Each write also deletes the entries older than  
+// `ttl_seconds`, so locations that are never looked up again do not stay on the volume  
+pub fn write(dir: &str, key: &str, body: &Value, ttl_seconds: u64) {  
+    if let Err(e) = try_write(dir, key, body) {  
+        if !WRITE_FAILURE_REPORTED.swap(true, Ordering::Relaxed) {  
+            eprintln!("Could not write to the response cache in {}: {}", dir, e);  
+        }  
+        return;  
+    }  
+
+    let _ = remove_expired(dir, ttl_seconds);  
+}  
+
+// Only files named like entries are touched; unreadable ones are removed too, as no lookup  
+// can ever use them  
+fn remove_expired(dir: &str, ttl_seconds: u64) -> io::Result<()> {  
+    for file in fs::read_dir(dir)? {  
+        let path = file?.path();  
+        let is_entry = path  
+            .file_name()  
+            .and_then(|name| name.to_str())  
+            .and_then(|name| name.strip_suffix(".json"))  
+            .is_some_and(|hash| hash.len() == 16 && hash.chars().all(|c| c.is_ascii_hexdigit()));  
+        if !is_entry {  
+            continue;  
+        }  
+
+        let age_seconds = fs::read_to_string(&path)  
+            .ok()  
+            .and_then(|entry| serde_json::from_str(&entry).ok())  
+            .and_then(|entry: Value| age(&entry));  
+        if age_seconds.is_none_or(|age_seconds| age_seconds >= ttl_seconds) {  
+            let _ = fs::remove_file(&path);  
+        }  
+    }  
+
+    Ok(())  
+}  
+
+fn try_write(dir: &str, key: &str, body: &Value) -> io::Result<()> {  
+    let entry = serde_json::json!({  
+        "key": key,  
+        "fetched_at": now(),  
+        "body": body,  
+    });  
+
+    fs::create_dir_all(dir)?;  
+    fs::write(entry_path(dir, key), entry.to_string())  
+}  
+
+fn age(entry: &Value) -> Option<u64> {  
+    let fetched_at = entry["fetched_at"].as_u64()?;  
+    Some(now().saturating_sub(fetched_at))  
+}  
+
+fn entry_path(dir: &str, key: &str) -> PathBuf {  
+    Path::new(dir).join(format!("{:016x}.json", fnv1a(key)))  
+}  
+
+// FNV-1a, stable across builds unlike the standard library hasher  
+fn fnv1a(key: &str) -> u64 {  
+    key.bytes()  
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3))  
+}  
+
+fn now() -> u64 {  
+    SystemTime::now()  
+        .duration_since(UNIX_EPOCH)  
+        .map(|d| d.as_secs())  
+        .unwrap_or(0)  
+}  
+
+#[cfg(test)]  
+mod tests {  
+    use super::*;  
+    use serde_json::json;  
+    use std::env;  
+    use std::process;  
+
+    fn temp_dir(name: &str) -> String {  
+        let dir = env::temp_dir().join(format!("pipeline3-cache-{}-{}", name, process::id()));  
+        let _ = fs::remove_dir_all(&dir);  
+        dir.to_string_lossy().into_owned()  
+    }  
+
+    #[test]  
+    fn written_entry_is_read_back() {  
+        let dir = temp_dir("round-trip");  
+        write(&dir, "weather|q=sydney,au|metric", &json!({ "name": "Sydney" }), 600);  
+
+        let cached = read(&dir, "weather|q=sydney,au|metric", 600).unwrap();  
+        assert_eq!(cached.body, json!({ "name": "Sydney" }));  
+        assert!(cached.age_seconds < 600);  
+        assert!(read(&dir, "weather|q=perth,au|metric", 600).is_none());  
+    }  
+
+    #[test]  
+    fn expired_entry_is_a_miss() {  
+        let dir = temp_dir("expired");  
+        let key = "weather|q=sydney,au|metric";  
+        fs::create_dir_all(&dir).unwrap();  
+        let entry = json!({ "key": key, "fetched_at": now() - 601, "body": {} });  
+        fs::write(entry_path(&dir, key), entry.to_string()).unwrap();  
+
+        assert!(read(&dir, key, 600).is_none());  
+        assert!(read(&dir, key, 3600).is_some());  
+    }  
+
+    #[test]  
+    fn write_deletes_expired_entries() {  
+        let dir = temp_dir("sweep");  
+        fs::create_dir_all(&dir).unwrap();  
+        let stale = "weather|q=perth,au|metric";  
+        let fresh = "weather|q=hobart,au|metric";  
+        let entry = |key: &str, age: u64| json!({ "key": key, "fetched_at": now() - age, "body": {} }).to_string();  
+        fs::write(entry_path(&dir, stale), entry(stale, 601)).unwrap();  
+        fs::write(entry_path(&dir, fresh), entry(fresh, 60)).unwrap();  
+        fs::write(entry_path(&dir, "corrupt"), "{").unwrap();  
+        fs::write(Path::new(&dir).join("notes.json"), "{").unwrap();  
+
+        write(&dir, "weather|q=sydney,au|metric", &json!({}), 600);  
+
+        assert!(!entry_path(&dir, stale).exists());  
+        assert!(!entry_path(&dir, "corrupt").exists());  
+        assert!(read(&dir, fresh, 600).is_some());  
+        assert!(read(&dir, "weather|q=sydney,au|metric", 600).is_some());  
+        assert!(Path::new(&dir).join("notes.json").exists());  
+    }  
+
+    #[test]  
+    fn entry_stored_under_another_key_is_rejected() {  
+        let dir = temp_dir("collision");  
+        let key = "weather|q=sydney,au|metric";  
+        fs::create_dir_all(&dir).unwrap();  
+        // Stands in for a different key whose name hashes to the same file  
+        let entry = json!({ "key": "weather|q=perth,au|metric", "fetched_at": now(), "body": {} });  
+        fs::write(entry_path(&dir, key), entry.to_string()).unwrap();  
+
+        assert!(read(&dir, key, 600).is_none());  
+    }  
+
+    #[test]  
+    fn failed_write_is_not_fatal() {  
+        let dir = temp_dir("unwritable");  
+        // A file where the directory should be makes every write fail  
+        fs::write(&dir, "").unwrap();  
+
+        write(&dir, "weather|q=sydney,au|metric", &json!({}), 600);  
+        assert!(try_write(&dir, "weather|q=sydney,au|metric", &json!({})).is_err());  
+        assert!(read(&dir, "weather|q=sydney,au|metric", 600).is_none());  
+    }  
+}
\ No newline at end of file
@@ -7,9 +7,12 @@ module_manifest!();
 
 pub fn main() {}  
 
+mod cache;  
+
 use serde::{Deserialize, Serialize};  
 use std::env;  
 use std::error::Error;  
+use std::fmt;  
 use tokio::runtime::Runtime;  
 use reqwest::Client;  
 use serde_json::Value;  
@@ -19,6 +22,9 @@ use chrono::prelude::*;
 #[derive(Debug, Serialize, Deserialize)]  
 pub struct WeatherData {  
     city: String,  
+    country: String,  
+    latitude: f64,  
+    longitude: f64,  
     date: String,  
     temperature: f32,  
     humidity: u8,  
@@ -27,66 +33,396 @@ pub struct WeatherData {
 }  
 
 #[marine]  
-pub fn generate_weather_report(zip_code: String) -> String {  
-    match call_openweathermap_api(&zip_code) {  
-        Ok(weather_data) => format!(  
-            "Current weather in {}:\n\  
-            Temperature: {}°C\n\  
-            Humidity: {}%\n\  
-            Wind speed: {} m/s\n\  
-            Weather description: {}\n\  
-            Date: {}",  
-            weather_data.city,  
-            weather_data.temperature,  
-            weather_data.humidity,  
-            weather_data.wind_speed,  
-            weather_data.weather_description,  
-            weather_data.date,  
+#[derive(Debug, Serialize, Deserialize)]  
+pub struct ForecastEntry {  
+    // Start of the 3-hour slot, UTC  
+    timestamp: String,  
+    temperature: f32,  
+    feels_like: f32,  
+    humidity: u8,  
+    wind_speed: f32,  
+    // Probability of precipitation, 0 to 1  
+    precipitation_probability: f32,  
+    weather_description: String,  
+}  
+
+#[marine]  
+#[derive(Debug, Serialize, Deserialize)]  
+pub struct WeatherForecast {  
+    city: String,  
+    country: String,  
+    latitude: f64,  
+    longitude: f64,  
+    entries: Vec<ForecastEntry>,  
+    from_cache: bool,  
+    cache_age_seconds: u64,  
+    error_code: String,  
+    error_message: String,  
+}  
+
+#[marine]  
+#[derive(Debug, Serialize, Deserialize)]  
+pub struct CurrentWeather {  
+    weather: WeatherData,  
+    from_cache: bool,  
+    cache_age_seconds: u64,  
+    // Empty on success, otherwise one of the WeatherError codes  
+    error_code: String,  
+    error_message: String,  
+}  
+
+const DEFAULT_BASE_URL: &str = "http://api.openweathermap.org/data/2.5";  
+const DEFAULT_CACHE_DIR: &str = "./cache";  
+const DEFAULT_CACHE_TTL_SECONDS: u64 = 600;  
+const UNITS: &str = "metric";  
+
+// Failures of the OpenWeatherMap calls, surfaced to callers as a stable code plus a message  
+#[derive(Debug)]  
+enum WeatherError {  
+    MissingApiKey,  
+    InvalidLocation(String),  
+    MissingCountry(String),  
+    Http(reqwest::StatusCode),  
+    Network(String),  
+    InvalidResponse(String),  
+    MissingField(&'static str),  
+}  
+
+impl WeatherError {  
+    fn code(&self) -> &'static str {  
+        match self {  
+            WeatherError::MissingApiKey => "missing_api_key",  
+            WeatherError::InvalidLocation(_) => "invalid_location",  
+            WeatherError::MissingCountry(_) => "missing_country",  
+            WeatherError::Http(status) if status.as_u16() == 401 => "invalid_api_key",  
+            WeatherError::Http(status) if status.as_u16() == 404 => "location_not_found",  
+            WeatherError::Http(status) if status.as_u16() == 429 => "rate_limited",  
+            WeatherError::Http(_) => "http_error",  
+            WeatherError::Network(_) => "network_error",  
+            WeatherError::InvalidResponse(_) => "invalid_response",  
+            WeatherError::MissingField(_) => "missing_field",  
+        }  
+    }  
+}  
+
+impl fmt::Display for WeatherError {  
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {  
+        match self {  
+            WeatherError::MissingApiKey => {  
+                write!(f, "API key is not set in environment variable 'OPENWEATHERMAP_API_KEY'")  
+            }  
+            WeatherError::InvalidLocation(location) => write!(f, "Unrecognised location: {}", location),  
+            WeatherError::MissingCountry(code) => write!(  
+                f,  
+                "Postal code {} needs a country code, e.g. '{},au', unless OPENWEATHERMAP_DEFAULT_COUNTRY is set",  
+                code, code  
+            ),  
+            WeatherError::Http(status) => write!(f, "Error response from API: {}", status),  
+            WeatherError::Network(e) => write!(f, "{}", e),  
+            WeatherError::InvalidResponse(e) => write!(f, "Invalid response from API: {}", e),  
+            WeatherError::MissingField(field) => write!(f, "Missing {}", field),  
+        }  
+    }  
+}  
+
+impl Error for WeatherError {}  
+
+// A location as accepted by the public functions:  
+//   "-33.87,151.21"                  latitude and longitude  
+//   "2000,au" or "90210"             postal code with a country code, which may only be left out  
+//                                    when OPENWEATHERMAP_DEFAULT_COUNTRY is set  
+//   "Sydney,au" or "Sydney"          city name with an optional country code  
+//   "Portland,OR,us"                 city name with a state and country code  
+#[derive(Debug)]  
+enum Location {  
+    Coordinates { latitude: f64, longitude: f64 },  
+    PostalCode { code: String, country: String },  
+    City { name: String, state: Option<String>, country: Option<String> },  
+}  
+
+impl Location {  
+    fn parse(location: &str, default_country: Option<&str>) -> Result<Location, WeatherError> {  
+        let parts: Vec<&str> = location.split(',').map(|part| part.trim()).collect();  
+        let invalid = || WeatherError::InvalidLocation(location.to_string());  
+
+        if parts.iter().any(|part| part.is_empty()) || parts.len() > 3 {  
+            return Err(invalid());  
+        }  
+
+        if let [latitude, longitude] = parts[..] {  
+            if let (Ok(latitude), Ok(longitude)) = (latitude.parse::<f64>(), longitude.parse::<f64>()) {  
+                if (-90.0..=90.0).contains(&latitude) && (-180.0..=180.0).contains(&longitude) {  
+                    return Ok(Location::Coordinates { latitude, longitude });  
+                }  
+                return Err(invalid());  
+            }  
+        }  
+
+        let country = match parts.last().filter(|_| parts.len() > 1) {  
+            Some(country) if country.len() == 2 && country.chars().all(|c| c.is_ascii_alphabetic()) => {  
+                Some(country.to_lowercase())  
+            }  
+            Some(_) => return Err(invalid()),  
+            None => None,  
+        };  
+        let state = if parts.len() == 3 { Some(parts[1].to_string()) } else { None };  
+
+        // Postal codes carry digits (90210, 2000, SW1A 1AA); city names do not. The same digits  
+        // are a postal code in several countries, so one is never assumed  
+        if parts[0].chars().any(|c| c.is_ascii_digit()) {  
+            if state.is_some() {  
+                return Err(invalid());  
+            }  
+            let country = country  
+                .or_else(|| default_country.map(|country| country.to_lowercase()))  
+                .ok_or_else(|| WeatherError::MissingCountry(parts[0].to_string()))?;  
+            Ok(Location::PostalCode {  
+                code: parts[0].to_string(),  
+                country,  
+            })  
+        } else {  
+            Ok(Location::City {  
+                name: parts[0].to_string(),  
+                state,  
+                country,  
+            })  
+        }  
+    }  
+
+    fn query(&self) -> Vec<(&'static str, String)> {  
+        match self {  
+            Location::Coordinates { latitude, longitude } => {  
+                vec![("lat", latitude.to_string()), ("lon", longitude.to_string())]  
+            }  
+            Location::PostalCode { code, country } => vec![("zip", format!("{},{}", code, country))],  
+            Location::City { name, state, country } => {  
+                let parts: Vec<&str> = std::iter::once(name.as_str())  
+                    .chain(state.as_deref())  
+                    .chain(country.as_deref())  
+                    .collect();  
+                vec![("q", parts.join(","))]  
+            }  
+        }  
+    }  
+
+    // Spellings of the same place share a cache entry; coordinates are rounded to about 1 km  
+    fn cache_key(&self) -> String {  
+        let normalise = |s: &str| s.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();  
+        match self {  
+            Location::Coordinates { latitude, longitude } => {  
+                format!("lat={:.2},lon={:.2}", latitude, longitude)  
+            }  
+            Location::PostalCode { code, country } => {  
+                format!("zip={},{}", normalise(code).replace(' ', ""), country)  
+            }  
+            Location::City { name, state: Some(state), country } => {  
+                format!("q={},{},{}", normalise(name), normalise(state), country.as_deref().unwrap_or(""))  
+            }  
+            Location::City { name, state: None, country } => {  
+                format!("q={},{}", normalise(name), country.as_deref().unwrap_or(""))  
+            }  
+        }  
+    }  
+}  
+
+// Whether a response was served from the cache, and how long ago it was fetched  
+struct CacheStatus {  
+    from_cache: bool,  
+    age_seconds: u64,  
+}  
+
+impl From<reqwest::Error> for WeatherError {  
+    fn from(e: reqwest::Error) -> Self {  
+        if e.is_decode() {  
+            WeatherError::InvalidResponse(e.to_string())  
+        } else {  
+            WeatherError::Network(e.to_string())  
+        }  
+    }  
+}  
+
+impl From<std::io::Error> for WeatherError {  
+    fn from(e: std::io::Error) -> Self {  
+        WeatherError::Network(e.to_string())  
+    }  
+}  
+
+#[marine]  
+pub fn get_current_weather(location: String) -> CurrentWeather {  
+    match call_openweathermap_api(&location) {  
+        Ok((weather, cache_status)) => CurrentWeather {  
+            weather,  
+            from_cache: cache_status.from_cache,  
+            cache_age_seconds: cache_status.age_seconds,  
+            error_code: "".to_string(),  
+            error_message: "".to_string(),  
+        },  
+        Err(e) => CurrentWeather {  
+            weather: empty_weather_data(),  
+            from_cache: false,  
+            cache_age_seconds: 0,  
+            error_code: e.code().to_string(),  
+            error_message: e.to_string(),  
+        },  
+    }  
+}  
+
+#[marine]  
+pub fn generate_weather_report(location: String) -> String {  
+    match call_openweathermap_api(&location) {  
+        Ok((weather_data, cache_status)) if cache_status.from_cache => format!(  
+            "{}\nServed from cache ({} s old)",  
+            format_weather_report(&weather_data),  
+            cache_status.age_seconds  
         ),  
+        Ok((weather_data, _)) => format_weather_report(&weather_data),  
         Err(e) => format!("Failed to connect to OpenWeatherMap API: {}", e),  
     }  
 }  
 
-fn call_openweathermap_api(zip_code: &str) -> Result<WeatherData, Box<dyn Error>> {  
+#[marine]  
+pub fn generate_weather_forecast(location: String) -> WeatherForecast {  
+    match call_openweathermap_forecast_api(&location) {  
+        Ok(forecast) => forecast,  
+        Err(e) => WeatherForecast {  
+            city: "".to_string(),  
+            country: "".to_string(),  
+            latitude: 0.0,  
+            longitude: 0.0,  
+            entries: vec![],  
+            from_cache: false,  
+            cache_age_seconds: 0,  
+            error_code: e.code().to_string(),  
+            error_message: e.to_string(),  
+        },  
+    }  
+}  
+
+// Presentation helper behind generate_weather_report  
+fn format_weather_report(weather_data: &WeatherData) -> String {  
+    format!(  
+        "Current weather in {}, {}:\n\  
+        Temperature: {}°C\n\  
+        Humidity: {}%\n\  
+        Wind speed: {} m/s\n\  
+        Weather description: {}\n\  
+        Date: {}",  
+        weather_data.city,  
+        weather_data.country,  
+        weather_data.temperature,  
+        weather_data.humidity,  
+        weather_data.wind_speed,  
+        weather_data.weather_description,  
+        weather_data.date,  
+    )  
+}  
+
+fn empty_weather_data() -> WeatherData {  
+    WeatherData {  
+        city: "".to_string(),  
+        country: "".to_string(),  
+        latitude: 0.0,  
+        longitude: 0.0,  
+        date: "".to_string(),  
+        temperature: 0.0,  
+        humidity: 0,  
+        wind_speed: 0.0,  
+        weather_description: "".to_string(),  
+    }  
+}  
+
+fn call_openweathermap_api(location: &str) -> Result<(WeatherData, CacheStatus), WeatherError> {  
+    fetch_openweathermap("weather", location, extract_relevant_data)  
+}  
+
+fn call_openweathermap_forecast_api(location: &str) -> Result<WeatherForecast, WeatherError> {  
+    let (forecast, cache_status) = fetch_openweathermap("forecast", location, extract_forecast_data)?;  
+
+    Ok(WeatherForecast {  
+        from_cache: cache_status.from_cache,  
+        cache_age_seconds: cache_status.age_seconds,  
+        ..forecast  
+    })  
+}  
+
+// Responses are only cached once `extract` accepts them, so a reply with missing  
+// fields is retried on the next call rather than replayed until it expires  
+fn fetch_openweathermap<T>(  
+    endpoint: &str,  
+    location: &str,  
+    extract: fn(Value) -> Result<T, WeatherError>,  
+) -> Result<(T, CacheStatus), WeatherError> {  
+    let location = Location::parse(location, get_env_default_country().as_deref())?;  
+
     let api_key = get_env_api_key();  
     if api_key.is_empty() {  
-        return Err("API key is not set in environment variable 'OPENWEATHERMAP_API_KEY'".into());  
+        return Err(WeatherError::MissingApiKey);  
     }  
 
-    let base_url = format!(  
-        "http://api.openweathermap.org/data/2.5/weather?zip={},us&appid={}&units=metric",  
-        zip_code, api_key  
-    );  
+    let base_url = get_env_base_url();  
+    let cache_dir = get_env_cache_dir();  
+    let cache_ttl = get_env_cache_ttl();  
+    // Keyed by provider too, so switching OPENWEATHERMAP_BASE_URL never serves the old one's replies  
+    let cache_key = format!("{}|{}|{}|{}", base_url, endpoint, location.cache_key(), UNITS);  
+    if cache_ttl > 0 {  
+        if let Some(cached) = cache::read(&cache_dir, &cache_key, cache_ttl) {  
+            let cache_status = CacheStatus {  
+                from_cache: true,  
+                age_seconds: cached.age_seconds,  
+            };  
+            return Ok((extract(cached.body)?, cache_status));  
+        }  
+    }  
+
+    let url = format!("{}/{}", base_url, endpoint);  
+    let mut params = location.query();  
+    params.push(("appid", api_key));  
+    params.push(("units", UNITS.to_string()));  
 
     let rt = Runtime::new()?;  
     let client = Client::new();  
 
     let res = rt.block_on(async {  
-        let response = client.get(&base_url).send().await?;  
+        let response = client.get(&url).query(&params).send().await?;  
         if response.status().is_success() {  
             let json_data = response.json::<Value>().await?;  
             Ok(json_data)  
         } else {  
-            Err(format!("Error response from API: {}", response.status()).into())  
+            Err(WeatherError::Http(response.status()))  
         }  
     })?;  
 
-    extract_relevant_data(res)  
+    let data = extract(res.clone())?;  
+    if cache_ttl > 0 {  
+        cache::write(&cache_dir, &cache_key, &res, cache_ttl);  
+    }  
+
+    let cache_status = CacheStatus {  
+        from_cache: false,  
+        age_seconds: 0,  
+    };  
+    Ok((data, cache_status))  
 }  
 
-fn extract_relevant_data(data: Value) -> Result<WeatherData, Box<dyn Error>> {  
-    let city_name = data["name"].as_str().ok_or("Missing city name")?.to_string();  
-    let date = Local::today().format("%Y-%m-%d").to_string();  
-    let temperature = data["main"]["temp"].as_f64().ok_or("Missing temperature")? as f32;  
-    let humidity = data["main"]["humidity"].as_u64().ok_or("Missing humidity")? as u8;  
-    let wind_speed = data["wind"]["speed"].as_f64().ok_or("Missing wind speed")? as f32;  
+fn extract_relevant_data(data: Value) -> Result<WeatherData, WeatherError> {  
+    let city_name = required(data["name"].as_str(), "city name")?.to_string();  
+    let country = required(data["sys"]["country"].as_str(), "country")?.to_string();  
+    let latitude = required(data["coord"]["lat"].as_f64(), "latitude")?;  
+    let longitude = required(data["coord"]["lon"].as_f64(), "longitude")?;  
+    let date = Local::now().format("%Y-%m-%d").to_string();  
+    let temperature = required(data["main"]["temp"].as_f64(), "temperature")? as f32;  
+    let humidity = required(data["main"]["humidity"].as_u64(), "humidity")? as u8;  
+    let wind_speed = required(data["wind"]["speed"].as_f64(), "wind speed")? as f32;  
     let weather_description = data["weather"][0]["description"]  
         .as_str()  
-        .ok_or("Missing weather description")?  
+        .ok_or(WeatherError::MissingField("weather description"))?  
         .to_string();  
 
     Ok(WeatherData {  
         city: city_name,  
+        country,  
+        latitude,  
+        longitude,  
         date,  
         temperature,  
         humidity,  
@@ -95,6 +431,144 @@ fn extract_relevant_data(data: Value) -> Result<WeatherData, Box<dyn Error>> {
     })  
 }  
 
+fn extract_forecast_data(data: Value) -> Result<WeatherForecast, WeatherError> {  
+    let city_name = required(data["city"]["name"].as_str(), "city name")?.to_string();  
+    let country = required(data["city"]["country"].as_str(), "country")?.to_string();  
+    let latitude = required(data["city"]["coord"]["lat"].as_f64(), "latitude")?;  
+    let longitude = required(data["city"]["coord"]["lon"].as_f64(), "longitude")?;  
+    let entries = data["list"]  
+        .as_array()  
+        .ok_or(WeatherError::MissingField("forecast list"))?  
+        .iter()  
+        .map(extract_forecast_entry)  
+        .collect::<Result<Vec<_>, _>>()?;  
+
+    Ok(WeatherForecast {  
+        city: city_name,  
+        country,  
+        latitude,  
+        longitude,  
+        entries,  
+        from_cache: false,  
+        cache_age_seconds: 0,  
+        error_code: "".to_string(),  
+        error_message: "".to_string(),  
+    })  
+}  
+
+fn extract_forecast_entry(entry: &Value) -> Result<ForecastEntry, WeatherError> {  
+    let dt = required(entry["dt"].as_i64(), "forecast timestamp")?;  
+    let timestamp = DateTime::from_timestamp(dt, 0)  
+        .ok_or_else(|| WeatherError::InvalidResponse(format!("invalid forecast timestamp {}", dt)))?  
+        .format("%Y-%m-%d %H:%M:%S")  
+        .to_string();  
+    let temperature = required(entry["main"]["temp"].as_f64(), "temperature")? as f32;  
+    let feels_like = required(entry["main"]["feels_like"].as_f64(), "feels-like temperature")? as f32;  
+    let humidity = required(entry["main"]["humidity"].as_u64(), "humidity")? as u8;  
+    let wind_speed = required(entry["wind"]["speed"].as_f64(), "wind speed")? as f32;  
+    let precipitation_probability = required(entry["pop"].as_f64(), "precipitation probability")? as f32;  
+    let weather_description = entry["weather"][0]["description"]  
+        .as_str()  
+        .ok_or(WeatherError::MissingField("weather description"))?  
+        .to_string();  
+
+    Ok(ForecastEntry {  
+        timestamp,  
+        temperature,  
+        feels_like,  
+        humidity,  
+        wind_speed,  
+        precipitation_probability,  
+        weather_description,  
+    })  
+}  
+
+fn required<T>(value: Option<T>, field: &'static str) -> Result<T, WeatherError> {  
+    value.ok_or(WeatherError::MissingField(field))  
+}  
+
 fn get_env_api_key() -> String {  
     env::var("OPENWEATHERMAP_API_KEY").unwrap_or_else(|_| "".to_string())  
+}  
+
+// The directory should be on a volume mounted into the module so that entries outlive a call  
+fn get_env_cache_dir() -> String {  
+    env::var("OPENWEATHERMAP_CACHE_DIR").unwrap_or_else(|_| DEFAULT_CACHE_DIR.to_string())  
+}  
+
+// Seconds a cached response stays fresh; 0 turns the cache off  
+fn get_env_cache_ttl() -> u64 {  
+    env::var("OPENWEATHERMAP_CACHE_TTL")  
+        .ok()  
+        .and_then(|ttl| ttl.trim().parse().ok())  
+        .unwrap_or(DEFAULT_CACHE_TTL_SECONDS)  
+}  
+
+// Country assumed for a postal code given without one; unset, such a code is rejected  
+fn get_env_default_country() -> Option<String> {  
+    env::var("OPENWEATHERMAP_DEFAULT_COUNTRY")  
+        .ok()  
+        .map(|country| country.trim().to_lowercase())  
+        .filter(|country| !country.is_empty())  
+}  
+
+// Overridable so that the module can be pointed at a local stub  
+fn get_env_base_url() -> String {  
+    env::var("OPENWEATHERMAP_BASE_URL")  
+        .map(|url| url.trim_end_matches('/').to_string())  
+        .unwrap_or_else(|_| DEFAULT_BASE_URL.to_string())  
+}  
+
+#[cfg(test)]  
+mod tests {  
+    use super::*;  
+
+    fn cache_key(location: &str) -> String {  
+        Location::parse(location, None).unwrap().cache_key()  
+    }  
+
+    #[test]  
+    fn spellings_of_a_city_share_a_key() {  
+        assert_eq!(cache_key("Sydney,AU"), "q=sydney,au");  
+        assert_eq!(cache_key(" sydney , au"), "q=sydney,au");  
+        assert_eq!(cache_key("New  York,us"), cache_key("new york,US"));  
+        assert_ne!(cache_key("Sydney"), cache_key("Sydney,au"));  
+        assert_eq!(cache_key("Portland, OR ,US"), cache_key("portland,or,us"));  
+        assert_ne!(cache_key("Portland,OR,us"), cache_key("Portland,ME,us"));  
+    }  
+
+    #[test]  
+    fn city_state_and_country_are_sent_together() {  
+        let location = Location::parse("Portland, OR, US", None).unwrap();  
+        assert_eq!(location.query(), vec![("q", "Portland,OR,us".to_string())]);  
+        assert!(Location::parse("Portland,OR,usa", None).is_err());  
+        assert!(Location::parse("Portland,,us", None).is_err());  
+        assert!(Location::parse("97201,OR,us", None).is_err());  
+    }  
+
+    #[test]  
+    fn spellings_of_a_postal_code_share_a_key() {  
+        assert_eq!(cache_key("SW1A 1AA,gb"), cache_key("sw1a1aa,GB"));  
+        assert_eq!(cache_key("2000,au"), "zip=2000,au");  
+    }  
+
+    #[test]  
+    fn postal_code_country_is_never_assumed() {  
+        match Location::parse("2000", None) {  
+            Err(WeatherError::MissingCountry(code)) => assert_eq!(code, "2000"),  
+            other => panic!("{:?}", other),  
+        }  
+
+        let location = Location::parse("2000", Some("AU")).unwrap();  
+        assert_eq!(location.query(), vec![("zip", "2000,au".to_string())]);  
+        let location = Location::parse("90210,us", Some("au")).unwrap();  
+        assert_eq!(location.query(), vec![("zip", "90210,us".to_string())]);  
+    }  
+
+    #[test]  
+    fn coordinates_are_rounded_to_two_decimals() {  
+        assert_eq!(cache_key("-33.8679,151.2073"), "lat=-33.87,lon=151.21");  
+        assert_eq!(cache_key("-33.871, 151.209"), "lat=-33.87,lon=151.21");  
+        assert_ne!(cache_key("-33.86,151.21"), cache_key("-33.87,151.21"));  
+    }  
 }
\ No newline at end of file
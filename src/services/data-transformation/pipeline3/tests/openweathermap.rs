@@ -0,0 +1,121 @@
+// tests/openweathermap.rs  
+
+// Runs the public functions against the mock server's recorded OpenWeatherMap responses  
+
+#[path = "../src/main.rs"]  
+#[allow(dead_code)]  
+mod pipeline3;  
+
+use std::env;  
+use std::sync::{Mutex, MutexGuard};  
+
+use mock_server::fixtures;  
+use mock_server::{MockServer, Response, Route};  
+use serde::Serialize;  
+use serde_json::Value;  
+
+use pipeline3::{generate_weather_forecast, get_current_weather};  
+
+static ENV_LOCK: Mutex<()> = Mutex::new(());  
+
+fn stub(routes: Vec<Route>) -> (MutexGuard<'static, ()>, MockServer) {  
+    let guard = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());  
+    let server = MockServer::start(routes).unwrap();  
+
+    env::set_var("OPENWEATHERMAP_API_KEY", "test-key");  
+    env::set_var("OPENWEATHERMAP_BASE_URL", server.url());  
+    env::set_var("OPENWEATHERMAP_CACHE_TTL", "0");  
+
+    (guard, server)  
+}  
+
+fn scenario(name: &str) -> Vec<Route> {  
+    fixtures::scenario(name).unwrap()  
+}  
+
+fn json<T: Serialize>(value: &T) -> Value {  
+    serde_json::to_value(value).unwrap()  
+}  
+
+#[test]  
+fn current_weather_is_read_from_the_response() {  
+    let (_guard, server) = stub(scenario("ok"));  
+
+    let result = json(&get_current_weather("Sydney,au".to_string()));  
+    assert_eq!(result["error_code"], "");  
+    assert_eq!(result["weather"]["city"], "Sydney");  
+    assert_eq!(result["weather"]["country"], "AU");  
+    assert_eq!(result["weather"]["humidity"], 68);  
+    assert_eq!(result["weather"]["weather_description"], "broken clouds");  
+    assert_eq!(result["from_cache"], false);  
+
+    let requests = server.requests();  
+    assert_eq!(requests.len(), 1);  
+    assert!(requests[0].starts_with("GET /weather?"), "{}", requests[0]);  
+    assert!(requests[0].contains("appid=test-key"), "{}", requests[0]);  
+    assert!(requests[0].contains("units=metric"), "{}", requests[0]);  
+}  
+
+#[test]  
+fn forecast_entries_are_read_from_the_response() {  
+    let (_guard, server) = stub(scenario("ok"));  
+
+    let result = json(&generate_weather_forecast("-33.87,151.21".to_string()));  
+    assert_eq!(result["error_code"], "");  
+    assert_eq!(result["city"], "Sydney");  
+    assert!(!result["entries"].as_array().unwrap().is_empty());  
+
+    let requests = server.requests();  
+    assert!(requests[0].starts_with("GET /forecast?"), "{}", requests[0]);  
+    assert!(requests[0].contains("lat=-33.87"), "{}", requests[0]);  
+}  
+
+#[test]  
+fn provider_failures_map_to_error_codes() {  
+    let cases = [  
+        ("unauthorized", "invalid_api_key"),  
+        ("not_found", "location_not_found"),  
+        ("malformed_json", "invalid_response"),  
+        ("missing_fields", "missing_field"),  
+    ];  
+
+    for (name, code) in cases.iter() {  
+        let (_guard, _server) = stub(scenario(name));  
+
+        let current = json(&get_current_weather("Sydney,au".to_string()));  
+        assert_eq!(current["error_code"], *code, "{}: {}", name, current["error_message"]);  
+        assert_ne!(current["error_message"], "");  
+
+        let forecast = json(&generate_weather_forecast("Sydney,au".to_string()));  
+        assert_eq!(forecast["error_code"], *code, "{}: {}", name, forecast["error_message"]);  
+        assert_eq!(forecast["entries"], Value::Array(vec![]));  
+    }  
+}  
+
+#[test]  
+fn rate_limiting_maps_to_its_own_code() {  
+    let (_guard, _server) = stub(vec![Route::new(  
+        "/weather",  
+        Response::new(429, r#"{"cod":429,"message":"Your account is temporary blocked"}"#),  
+    )]);  
+
+    let result = json(&get_current_weather("Sydney,au".to_string()));  
+    assert_eq!(result["error_code"], "rate_limited");  
+}  
+
+#[test]  
+fn requests_are_not_sent_without_a_key_or_a_valid_location() {  
+    let (_guard, server) = stub(scenario("ok"));  
+
+    let result = json(&get_current_weather("Sydney,australia".to_string()));  
+    assert_eq!(result["error_code"], "invalid_location");  
+
+    let result = json(&get_current_weather("2000".to_string()));  
+    assert_eq!(result["error_code"], "missing_country");  
+
+    env::remove_var("OPENWEATHERMAP_API_KEY");  
+    let result = json(&get_current_weather("Sydney,au".to_string()));  
+    assert_eq!(result["error_code"], "missing_api_key");  
+
+    assert!(server.requests().is_empty());  
+}
\ No newline at end of file
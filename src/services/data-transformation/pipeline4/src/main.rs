@@ -10,7 +10,6 @@ pub fn main() {}
 use polars::prelude::*;  
 use serde::{Deserialize, Serialize};  
 use std::error::Error;  
-use std::fs::File;  
 
 #[marine]  
 #[derive(Debug, Serialize, Deserialize)]  
@@ -41,16 +40,30 @@ pub fn generate_environmental_report(city: String) -> EnvironmentalReport {
         }  
     };  
 
+    let city = match location_match::resolve_location_in(&df, "City", &city) {  
+        Ok(city) => city,  
+        Err(e) => {  
+            return EnvironmentalReport {  
+                city,  
+                avg_pm25: None,  
+                avg_pm10: None,  
+                avg_solar_radiation: None,  
+                avg_co2_emissions: None,  
+                error: e,  
+            }  
+        }  
+    };  
+
     let filtered_data = get_city_data(&df, &city);  
 
     if filtered_data.height() == 0 {  
         return EnvironmentalReport {  
+            error: format!("No data available for {}.", city),  
             city,  
             avg_pm25: None,  
             avg_pm10: None,  
             avg_solar_radiation: None,  
             avg_co2_emissions: None,  
-            error: format!("No data available for {}.", city),  
         };  
     }  
 
@@ -83,8 +96,7 @@ fn get_city_data(df: &DataFrame, city: &str) -> DataFrame {
     let mask = city_column  
         .utf8()  
         .unwrap()  
-        .equal(city)  
-        .unwrap();  
+        .equal(city);  
 
     df.filter(&mask).unwrap_or_else(|_| DataFrame::default())  
 }  
@@ -0,0 +1,195 @@
+// src/classifier.rs  
+
+// Logistic regression trained with mini-batch gradient descent on standardised features  
+
+const EPOCHS: usize = 10;  
+const BATCH_SIZE: usize = 64;  
+const LEARNING_RATE: f32 = 0.05;  
+const L2_PENALTY: f32 = 1e-4;  
+
+// SplitMix64, so that shuffles depend only on the seed and not on the platform  
+pub struct Rng {  
+    state: u64,  
+}  
+
+impl Rng {  
+    pub fn new(seed: u64) -> Rng {  
+        Rng { state: seed }  
+    }  
+
+    fn next_u64(&mut self) -> u64 {  
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);  
+        let mut z = self.state;  
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);  
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);  
+        z ^ (z >> 31)  
+    }  
+
+    // Fisher-Yates  
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {  
+        for i in (1..items.len()).rev() {  
+            let j = (self.next_u64() % (i as u64 + 1)) as usize;  
+            items.swap(i, j);  
+        }  
+    }  
+}  
+
+pub struct Model {  
+    // Per-feature mean and standard deviation taken from the training rows;  
+    // missing values are replaced with the mean, i.e. zero after scaling  
+    means: Vec<f32>,  
+    stds: Vec<f32>,  
+    weights: Vec<f32>,  
+    bias: f32,  
+}  
+
+impl Model {  
+    pub fn train(rows: &[Vec<Option<f32>>], labels: &[bool], rng: &mut Rng) -> Model {  
+        let feature_count = rows.first().map_or(0, |row| row.len());  
+        let (means, stds) = feature_scaling(rows, feature_count);  
+
+        let mut model = Model {  
+            means,  
+            stds,  
+            weights: vec![0.0; feature_count],  
+            bias: 0.0,  
+        };  
+
+        let scaled: Vec<Vec<f32>> = rows.iter().map(|row| model.scale(row)).collect();  
+        let mut order: Vec<usize> = (0..scaled.len()).collect();  
+
+        for _ in 0..EPOCHS {  
+            rng.shuffle(&mut order);  
+
+            for batch in order.chunks(BATCH_SIZE) {  
+                let mut weight_gradient = vec![0.0; feature_count];  
+                let mut bias_gradient = 0.0;  
+
+                for &i in batch {  
+                    let error = sigmoid(model.logit(&scaled[i])) - if labels[i] { 1.0 } else { 0.0 };  
+                    for (gradient, x) in weight_gradient.iter_mut().zip(&scaled[i]) {  
+                        *gradient += error * x;  
+                    }  
+                    bias_gradient += error;  
+                }  
+
+                let n = batch.len() as f32;  
+                for (weight, gradient) in model.weights.iter_mut().zip(&weight_gradient) {  
+                    *weight -= LEARNING_RATE * (gradient / n + L2_PENALTY * *weight);  
+                }  
+                model.bias -= LEARNING_RATE * bias_gradient / n;  
+            }  
+        }  
+
+        model  
+    }  
+
+    pub fn predict(&self, row: &[Option<f32>]) -> f32 {  
+        sigmoid(self.logit(&self.scale(row)))  
+    }  
+
+    // Each feature's share of the log-odds for this row, relative to an average day  
+    pub fn contributions(&self, row: &[Option<f32>]) -> Vec<f32> {  
+        self.scale(row)  
+            .iter()  
+            .zip(&self.weights)  
+            .map(|(x, weight)| x * weight)  
+            .collect()  
+    }  
+
+    fn scale(&self, row: &[Option<f32>]) -> Vec<f32> {  
+        row.iter()  
+            .zip(self.means.iter().zip(&self.stds))  
+            .map(|(value, (mean, std))| value.map_or(0.0, |v| (v - mean) / std))  
+            .collect()  
+    }  
+
+    fn logit(&self, scaled: &[f32]) -> f32 {  
+        self.bias + scaled.iter().zip(&self.weights).map(|(x, w)| x * w).sum::<f32>()  
+    }  
+}  
+
+fn feature_scaling(rows: &[Vec<Option<f32>>], feature_count: usize) -> (Vec<f32>, Vec<f32>) {  
+    (0..feature_count)  
+        .map(|j| {  
+            let values: Vec<f32> = rows.iter().filter_map(|row| row[j]).collect();  
+            if values.is_empty() {  
+                return (0.0, 1.0);  
+            }  
+            let mean = values.iter().sum::<f32>() / values.len() as f32;  
+            let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / values.len() as f32;  
+            // Constant columns would divide by zero  
+            let std = if variance > 0.0 { variance.sqrt() } else { 1.0 };  
+            (mean, std)  
+        })  
+        .unzip()  
+}  
+
+fn sigmoid(x: f32) -> f32 {  
+    1.0 / (1.0 + (-x).exp())  
+}  
+
+#[cfg(test)]  
+mod tests {  
+    use super::*;  
+
+    fn shuffled(seed: u64) -> Vec<usize> {  
+        let mut items: Vec<usize> = (0..50).collect();  
+        Rng::new(seed).shuffle(&mut items);  
+        items  
+    }  
+
+    // One informative feature, one constant and one mostly missing  
+    fn training_set() -> (Vec<Vec<Option<f32>>>, Vec<bool>) {  
+        (0..200)  
+            .map(|i| {  
+                let x = (i % 20) as f32 - 10.0;  
+                let sparse = if i % 7 == 0 { Some(i as f32) } else { None };  
+                (vec![Some(x), Some(3.0), sparse], x > 0.0)  
+            })  
+            .unzip()  
+    }  
+
+    #[test]  
+    fn rng_is_deterministic_for_a_seed() {  
+        let mut a = Rng::new(7);  
+        let mut b = Rng::new(7);  
+        let first: Vec<u64> = (0..10).map(|_| a.next_u64()).collect();  
+        let second: Vec<u64> = (0..10).map(|_| b.next_u64()).collect();  
+        assert_eq!(first, second);  
+
+        assert_eq!(shuffled(7), shuffled(7));  
+        assert_ne!(shuffled(7), shuffled(8));  
+    }  
+
+    #[test]  
+    fn shuffle_is_a_permutation() {  
+        let mut items = shuffled(42);  
+        items.sort();  
+        assert_eq!(items, (0..50).collect::<Vec<_>>());  
+    }  
+
+    #[test]  
+    fn training_is_reproducible_from_the_seed() {  
+        let (rows, labels) = training_set();  
+        let a = Model::train(&rows, &labels, &mut Rng::new(3));  
+        let b = Model::train(&rows, &labels, &mut Rng::new(3));  
+        assert_eq!(a.weights, b.weights);  
+        assert_eq!(a.bias, b.bias);  
+    }  
+
+    #[test]  
+    fn model_learns_a_separable_feature() {  
+        let (rows, labels) = training_set();  
+        let model = Model::train(&rows, &labels, &mut Rng::new(1));  
+
+        assert!(model.predict(&[Some(8.0), Some(3.0), None]) > 0.6);  
+        assert!(model.predict(&[Some(-8.0), Some(3.0), None]) < 0.4);  
+
+        // The informative feature carries the log-odds; the constant one scales to zero  
+        let contributions = model.contributions(&[Some(8.0), Some(3.0), None]);  
+        assert!(contributions[0] > 0.0);  
+        assert_eq!(contributions[1], 0.0);  
+        assert_eq!(contributions[2], 0.0);  
+    }  
+}
\ No newline at end of file
@@ -0,0 +1,307 @@
+// src/lib.rs  
+
+use marine_rs_sdk::marine;  
+use marine_rs_sdk::module_manifest;  
+
+module_manifest!();  
+
+pub fn main() {}  
+
+mod classifier;  
+
+use serde::{Deserialize, Serialize};  
+use std::error::Error;  
+use std::path::Path;  
+
+use chrono::NaiveDate;  
+use polars::prelude::*;  
+
+use classifier::{Model, Rng};  
+
+#[marine]  
+#[derive(Debug, Serialize, Deserialize)]  
+pub struct FeatureContribution {  
+    feature: String,  
+    value: Option<f32>,  
+    // Change in log-odds of rain compared with an average day  
+    contribution: f32,  
+}  
+
+#[marine]  
+#[derive(Debug, Serialize, Deserialize)]  
+pub struct ModelEvaluation {  
+    seed: u64,  
+    training_rows: u32,  
+    test_rows: u32,  
+    accuracy: f32,  
+    brier_score: f32,  
+    // Share of rainy days in the test rows, as a baseline for the scores above  
+    base_rate: f32,  
+}  
+
+#[marine]  
+#[derive(Debug, Serialize, Deserialize)]  
+pub struct RainPrediction {  
+    error: String,  
+    city: String,  
+    date: String,  
+    probability: f32,  
+    // "Yes" or "No" when the dataset already records the outcome, otherwise empty  
+    observed: String,  
+    top_features: Vec<FeatureContribution>,  
+    evaluation: ModelEvaluation,  
+}  
+
+const TEST_SHARE: f32 = 0.2;  
+const TOP_FEATURES: usize = 5;  
+// Fewer labelled days than this before the requested date cannot support a split  
+const MIN_TRAINING_DAYS: usize = 30;  
+
+const FEATURES: [&str; 17] = [  
+    "MinTemp",  
+    "MaxTemp",  
+    "Rainfall",  
+    "Evaporation",  
+    "Sunshine",  
+    "WindGustSpeed",  
+    "WindSpeed9am",  
+    "WindSpeed3pm",  
+    "Humidity9am",  
+    "Humidity3pm",  
+    "Pressure9am",  
+    "Pressure3pm",  
+    "Cloud9am",  
+    "Cloud3pm",  
+    "Temp9am",  
+    "Temp3pm",  
+    "RainToday",  
+];  
+
+#[marine]  
+pub fn predict_rain_tomorrow(city: String, date: String, seed: u64) -> RainPrediction {  
+    let file_path = "./weatherAUS.csv";  
+
+    let failed_prediction = |error: String| RainPrediction {  
+        error,  
+        city: city.clone(),  
+        date: date.clone(),  
+        probability: 0.0,  
+        observed: "".to_string(),  
+        top_features: vec![],  
+        evaluation: empty_evaluation(seed),  
+    };  
+
+    let day = match NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d") {  
+        Ok(day) => day,  
+        Err(_) => return failed_prediction(format!("Invalid date: {}. Expected YYYY-MM-DD.", date)),  
+    };  
+
+    let df = match load_weather_data(file_path) {  
+        Ok(df) => df,  
+        Err(e) => return failed_prediction(format!("Error loading weather data: {}", e)),  
+    };  
+
+    let city = match location_match::resolve_location_in(&df, "Location", &city) {  
+        Ok(city) => city,  
+        Err(e) => return failed_prediction(e),  
+    };  
+
+    let row = match get_day_row(&df, &city, day) {  
+        Some(row) => row,  
+        None => return failed_prediction(format!("No data available for {} on {}.", city, day)),  
+    };  
+
+    // Only earlier days are used, so a historical query is never scored by a model that saw its outcome  
+    let (model, evaluation) = match train_model(&df, seed, Some(day)) {  
+        Ok(trained) => trained,  
+        Err(e) => return failed_prediction(e),  
+    };  
+
+    let mut top_features: Vec<FeatureContribution> = FEATURES  
+        .iter()  
+        .zip(&row)  
+        .zip(model.contributions(&row))  
+        .map(|((feature, value), contribution)| FeatureContribution {  
+            feature: feature.to_string(),  
+            value: *value,  
+            contribution,  
+        })  
+        .collect();  
+    top_features.sort_by(|a, b| b.contribution.abs().total_cmp(&a.contribution.abs()));  
+    top_features.truncate(TOP_FEATURES);  
+
+    let observed = get_labels(&df)  
+        .into_iter()  
+        .zip(get_row_mask(&df, &city, day))  
+        .find(|(_, matches)| *matches)  
+        .and_then(|(label, _)| label)  
+        .map(|rain| if rain { "Yes" } else { "No" }.to_string())  
+        .unwrap_or_default();  
+
+    RainPrediction {  
+        error: "".to_string(),  
+        city,  
+        date: day.format("%Y-%m-%d").to_string(),  
+        probability: model.predict(&row),  
+        observed,  
+        top_features,  
+        evaluation,  
+    }  
+}  
+
+#[marine]  
+pub fn evaluate_rain_classifier(seed: u64) -> ModelEvaluation {  
+    let file_path = "./weatherAUS.csv";  
+
+    match load_weather_data(file_path) {  
+        Ok(df) => match train_model(&df, seed, None) {  
+            Ok((_, evaluation)) => evaluation,  
+            Err(_) => empty_evaluation(seed),  
+        },  
+        Err(_) => empty_evaluation(seed),  
+    }  
+}  
+
+// Trains a single model across all locations on the days before `until` (every day when None)  
+// and scores it on the most recent TEST_SHARE of those days. Daily rows are autocorrelated, so a  
+// random split would put neighbouring days on both sides and flatter the held-out metrics  
+fn train_model(df: &DataFrame, seed: u64, until: Option<NaiveDate>) -> Result<(Model, ModelEvaluation), String> {  
+    let rows = get_feature_rows(df);  
+
+    // Rows without a date or a RainTomorrow label can be neither trained on nor scored  
+    let labelled: Vec<(NaiveDate, Vec<Option<f32>>, bool)> = get_dates(df)  
+        .into_iter()  
+        .zip(rows)  
+        .zip(get_labels(df))  
+        .filter_map(|((date, row), label)| Some((date?, row, label?)))  
+        .filter(|(date, _, _)| until.is_none_or(|until| *date < until))  
+        .collect();  
+
+    let mut days: Vec<NaiveDate> = labelled.iter().map(|(date, _, _)| *date).collect();  
+    days.sort();  
+    days.dedup();  
+    if days.len() < MIN_TRAINING_DAYS {  
+        return Err(match until {  
+            Some(until) => format!("Not enough labelled days before {} to train the classifier.", until),  
+            None => "Not enough labelled days to train the classifier.".to_string(),  
+        });  
+    }  
+
+    let test_start = days[days.len() - (days.len() as f32 * TEST_SHARE).ceil() as usize];  
+    let (test, train): (Vec<_>, Vec<_>) = labelled.into_iter().partition(|(date, _, _)| *date >= test_start);  
+
+    let train_rows: Vec<Vec<Option<f32>>> = train.iter().map(|(_, row, _)| row.clone()).collect();  
+    let train_labels: Vec<bool> = train.iter().map(|(_, _, label)| *label).collect();  
+    let model = Model::train(&train_rows, &train_labels, &mut Rng::new(seed));  
+
+    let n = test.len().max(1) as f32;  
+    let predictions: Vec<(f32, bool)> = test.iter().map(|(_, row, label)| (model.predict(row), *label)).collect();  
+    let correct = predictions.iter().filter(|(p, label)| (*p >= 0.5) == *label).count();  
+    let squared_error: f32 = predictions  
+        .iter()  
+        .map(|(p, label)| (p - if *label { 1.0 } else { 0.0 }).powi(2))  
+        .sum();  
+    let rainy = predictions.iter().filter(|(_, label)| *label).count();  
+
+    let evaluation = ModelEvaluation {  
+        seed,  
+        training_rows: train.len() as u32,  
+        test_rows: test.len() as u32,  
+        accuracy: correct as f32 / n,  
+        brier_score: squared_error / n,  
+        base_rate: rainy as f32 / n,  
+    };  
+
+    Ok((model, evaluation))  
+}  
+
+fn empty_evaluation(seed: u64) -> ModelEvaluation {  
+    ModelEvaluation {  
+        seed,  
+        training_rows: 0,  
+        test_rows: 0,  
+        accuracy: 0.0,  
+        brier_score: 0.0,  
+        base_rate: 0.0,  
+    }  
+}  
+
+fn load_weather_data<P: AsRef<Path>>(file_path: P) -> Result<DataFrame, Box<dyn Error>> {  
+    let df = CsvReader::from_path(file_path.as_ref())?  
+        .infer_schema(None)  
+        .has_header(true)  
+        .finish()?;  
+    Ok(df)  
+}  
+
+fn yes_no(df: &DataFrame, column_name: &str) -> Vec<Option<bool>> {  
+    match df.column(column_name).ok().and_then(|s| s.utf8().ok()) {  
+        Some(values) => values  
+            .into_iter()  
+            .map(|opt_s| match opt_s {  
+                Some("Yes") => Some(true),  
+                Some("No") => Some(false),  
+                _ => None,  
+            })  
+            .collect(),  
+        None => vec![None; df.height()],  
+    }  
+}  
+
+fn get_dates(df: &DataFrame) -> Vec<Option<NaiveDate>> {  
+    df.column("Date")  
+        .unwrap()  
+        .utf8()  
+        .unwrap()  
+        .into_iter()  
+        .map(|opt_s| opt_s.and_then(|s| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()))  
+        .collect()  
+}  
+
+fn get_labels(df: &DataFrame) -> Vec<Option<bool>> {  
+    yes_no(df, "RainTomorrow")  
+}  
+
+// One row per record with the FEATURES in order; RainToday is encoded as 0 or 1  
+fn get_feature_rows(df: &DataFrame) -> Vec<Vec<Option<f32>>> {  
+    let columns: Vec<Vec<Option<f32>>> = FEATURES  
+        .iter()  
+        .map(|feature| {  
+            if *feature == "RainToday" {  
+                return yes_no(df, feature)  
+                    .into_iter()  
+                    .map(|rain| rain.map(|r| if r { 1.0 } else { 0.0 }))  
+                    .collect();  
+            }  
+            df.column(feature)  
+                .ok()  
+                .and_then(|s| s.cast(&DataType::Float32).ok())  
+                .map(|s| s.f32().unwrap().into_iter().collect())  
+                .unwrap_or_else(|| vec![None; df.height()])  
+        })  
+        .collect();  
+
+    (0..df.height())  
+        .map(|i| columns.iter().map(|column| column[i]).collect())  
+        .collect()  
+}  
+
+fn get_row_mask(df: &DataFrame, city: &str, day: NaiveDate) -> Vec<bool> {  
+    let day = day.format("%Y-%m-%d").to_string();  
+    let locations = df.column("Location").unwrap().utf8().unwrap();  
+    let dates = df.column("Date").unwrap().utf8().unwrap();  
+
+    locations  
+        .into_iter()  
+        .zip(dates)  
+        .map(|(location, date)| location == Some(city) && date == Some(day.as_str()))  
+        .collect()  
+}  
+
+fn get_day_row(df: &DataFrame, city: &str, day: NaiveDate) -> Option<Vec<Option<f32>>> {  
+    get_feature_rows(df)  
+        .into_iter()  
+        .zip(get_row_mask(df, city, day))  
+        .find(|(_, matches)| *matches)  
+        .map(|(row, _)| row)  
+}
\ No newline at end of file